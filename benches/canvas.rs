@@ -0,0 +1,93 @@
+//! Compares finding the nearest hit of every camera ray with `Bvh` to testing every object with every ray.
+//! Both paths compute the same distances, shading is not timed.
+//!
+//! `> cargo bench --bench canvas`
+
+use std::f32::consts::PI;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rusty_gaym_engine::engine::*;
+use rusty_gaym_engine::math::*;
+
+const WIDTH: usize = 120;
+const HEIGHT: usize = 40;
+const FRAMES: u32 = 5;
+
+/// Cube of `n`×`n`×`n` spheres in front of camera and a floor plane.
+fn scene(n: usize) -> Vec<SceneObject> {
+    let mut scene = vec![];
+    for x in 0..n {
+        for y in 0..n {
+            for z in 0..n {
+                let position = Vector::from_xyz(
+                    5.0 + x as f32,
+                    y as f32 - 0.5 * n as f32,
+                    z as f32 - 0.5 * n as f32,
+                );
+                let transform = Transform::new(position, &Vector::from_xyz(1.0, 0.0, 0.0)).unwrap();
+                scene.push(SceneObject::new(transform, Box::new(Hyperellipsoid::new3d(0.3, 0.3, 0.3))));
+            }
+        }
+    }
+    let floor = Transform::new_from_coords(0.0, 0.0, -(n as f32), 0.0, 0.0, 1.0).unwrap();
+    scene.push(SceneObject::new(floor, Box::new(Hyperplane)));
+    scene
+}
+
+fn brute_force(objects: &[&SceneObject], rays: &Matrix<Ray>) -> Matrix<Option<f32>> {
+    Matrix::from_rule(rays.rows_count(), rays.cols_count(), |i, j| {
+        objects
+            .iter()
+            .filter_map(|object| object.game_object.intersection_distance(&object.transform, &rays[i][j]))
+            .filter(|distance| *distance >= 0.0)
+            .min_by(|a, b| a.total_cmp(b))
+    })
+}
+
+/// Builds tree like `Canvas::update` does every frame and finds the nearest hits with it.
+fn bvh(objects: &[&SceneObject], rays: &Matrix<Ray>) -> Matrix<Option<f32>> {
+    let boxes: Vec<Option<Aabb>> = objects
+        .iter()
+        .map(|object| object.game_object.bounding_box(&object.transform))
+        .collect();
+    let bvh = Bvh::new(&boxes);
+    Matrix::from_rule(rays.rows_count(), rays.cols_count(), |i, j| {
+        let ray = &rays[i][j];
+        bvh.nearest(ray, |k| objects[k].game_object.intersection_distance(&objects[k].transform, ray))
+            .map(|(_, distance)| distance)
+    })
+}
+
+fn average(mut frame: impl FnMut()) -> Duration {
+    let started = Instant::now();
+    for _ in 0..FRAMES {
+        frame();
+    }
+    started.elapsed() / FRAMES
+}
+
+fn main() {
+    let config = GameConfig {
+        screen_width: WIDTH,
+        screen_height: HEIGHT,
+        camera_fov: PI / 2.0,
+        ..GameConfig::default()
+    };
+    let camera = Camera::new(Transform::default(), &config);
+    let rays = camera.get_hyperslice_rays_matrix(HEIGHT, WIDTH);
+
+    println!("{:>8} {:>14} {:>14}", "objects", "brute force", "bvh");
+    for n in [2, 4, 8, 12] {
+        let scene = scene(n);
+        let objects: Vec<&SceneObject> = scene.iter().collect();
+
+        let brute = average(|| {
+            black_box(brute_force(&objects, &rays));
+        });
+        let accelerated = average(|| {
+            black_box(bvh(&objects, &rays));
+        });
+        println!("{:>8} {:>14?} {:>14?}", objects.len(), brute, accelerated);
+    }
+}
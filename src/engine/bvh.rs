@@ -0,0 +1,385 @@
+use crate::math::*;
+
+
+/// Maximal number of objects in a leaf of `Bvh`.
+const MAX_LEAF_SIZE: usize = 2;
+/// `Bvh::update` rebuilds the tree instead of refitting when refitted boxes
+/// became this many times larger than boxes of the freshly built tree.
+const REBUILD_AREA_RATIO: f32 = 2.0;
+
+#[derive(Debug, Clone)]
+enum BvhNodeKind {
+    /// Objects `objects[first..first + count]` of the tree.
+    Leaf { first: usize, count: usize },
+    Inner { left: usize, right: usize },
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    aabb: Aabb,
+    kind: BvhNodeKind,
+}
+
+/// Bounding volume hierarchy over objects of a scene.
+///
+/// Objects are referred by their indices in the slice of bounding boxes the tree is built from.
+/// Objects without bounding box, like `Hyperplane`, are kept in a separate list
+/// and tested with every ray.
+/// Tree is built with surface area heuristic.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    /// Children of node always have greater indices than the node.
+    nodes: Vec<BvhNode>,
+    objects: Vec<usize>,
+    unbounded: Vec<usize>,
+    objects_count: usize,
+    /// Sum of surface areas of nodes right after build.
+    built_area: f32,
+}
+
+impl Bvh {
+    /// Builds tree over objects with given bounding boxes.
+    /// Boxes with dimension different from the first box are treated as unbounded.
+    pub fn new(boxes: &[Option<Aabb>]) -> Bvh {
+        let dim = boxes.iter().flatten().map(|aabb| aabb.dim()).next();
+        let (mut objects, unbounded): (Vec<usize>, Vec<usize>) = (0..boxes.len())
+            .partition(|i| boxes[*i].as_ref().is_some_and(|aabb| Some(aabb.dim()) == dim));
+
+        let mut bvh = Bvh { nodes: vec![], objects: vec![], unbounded, objects_count: boxes.len(), built_area: 0.0 };
+        if !objects.is_empty() {
+            let centers: Vec<Option<Vector<f32>>> = boxes
+                .iter()
+                .map(|aabb| aabb.as_ref().map(|aabb| aabb.center()))
+                .collect();
+            bvh.build_node(boxes, &centers, &mut objects, 0);
+        }
+        bvh.objects = objects;
+        bvh.built_area = bvh.total_area();
+        bvh
+    }
+
+    /// Indices of objects without bounding box.
+    pub fn unbounded(&self) -> &[usize] {
+        &self.unbounded
+    }
+
+    /// Returns number of nodes in the tree.
+    pub fn nodes_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns bounding box of all bounded objects.
+    pub fn root_box(&self) -> Option<&Aabb> {
+        self.nodes.first().map(|node| &node.aabb)
+    }
+
+    /// Builds subtree over `items` which start at `first` in the list of objects.
+    /// Returns index of the subtree root.
+    fn build_node(
+        &mut self,
+        boxes: &[Option<Aabb>],
+        centers: &[Option<Vector<f32>>],
+        items: &mut [usize],
+        first: usize,
+    ) -> usize {
+        let aabb = items
+            .iter()
+            .map(|i| boxes[*i].clone().unwrap())
+            .reduce(|a, b| a.union(&b))
+            .unwrap();
+        let index = self.nodes.len();
+        self.nodes.push(BvhNode { aabb, kind: BvhNodeKind::Leaf { first, count: items.len() } });
+        if items.len() <= MAX_LEAF_SIZE {
+            return index;
+        }
+
+        // Split along the axis where centers are spread the most.
+        let center = |i: &usize| centers[*i].as_ref().unwrap();
+        let center_box = items
+            .iter()
+            .map(|i| Aabb::around(center(i), 0.0))
+            .reduce(|a, b| a.union(&b))
+            .unwrap();
+        let axis = center_box.longest_axis();
+        items.sort_by(|a, b| center(a)[axis].total_cmp(&center(b)[axis]));
+
+        // Surface area heuristic: cost of split after `k` items is
+        // `area(left) * k + area(right) * (n - k)`.
+        let n = items.len();
+        let mut right_areas = vec![0.0; n];
+        let mut right_box = boxes[items[n - 1]].clone().unwrap();
+        for k in (1..n).rev() {
+            right_box = right_box.union(boxes[items[k]].as_ref().unwrap());
+            right_areas[k] = right_box.surface_area();
+        }
+        let mut left_box = boxes[items[0]].clone().unwrap();
+        let mut best = (f32::INFINITY, n / 2);
+        for k in 1..n {
+            left_box = left_box.union(boxes[items[k - 1]].as_ref().unwrap());
+            let cost = left_box.surface_area() * k as f32 + right_areas[k] * (n - k) as f32;
+            if cost < best.0 {
+                best = (cost, k);
+            }
+        }
+
+        let (left_items, right_items) = items.split_at_mut(best.1);
+        let left = self.build_node(boxes, centers, left_items, first);
+        let right = self.build_node(boxes, centers, right_items, first + best.1);
+        self.nodes[index].kind = BvhNodeKind::Inner { left, right };
+        index
+    }
+
+    fn total_area(&self) -> f32 {
+        self.nodes.iter().map(|node| node.aabb.surface_area()).sum()
+    }
+
+    /// Recomputes boxes of nodes keeping structure of the tree.
+    /// If objects were added, removed or became unbounded returns error.
+    pub fn refit(&mut self, boxes: &[Option<Aabb>]) -> Result<(), ()> {
+        if boxes.len() != self.objects_count || (self.nodes.is_empty() && boxes.iter().any(Option::is_some)) {
+            return Err(());
+        }
+        let dim = self.root_box().map(|aabb| aabb.dim());
+        let fits = |i: &usize| boxes[*i].as_ref().is_some_and(|aabb| Some(aabb.dim()) == dim);
+        if !self.objects.iter().all(fits) || self.unbounded.iter().any(fits) {
+            return Err(());
+        }
+        for index in (0..self.nodes.len()).rev() {
+            self.nodes[index].aabb = match self.nodes[index].kind {
+                BvhNodeKind::Leaf { first, count } => self.objects[first..first + count]
+                    .iter()
+                    .map(|i| boxes[*i].clone().unwrap())
+                    .reduce(|a, b| a.union(&b))
+                    .unwrap(),
+                BvhNodeKind::Inner { left, right } => self.nodes[left].aabb.union(&self.nodes[right].aabb),
+            };
+        }
+        Ok(())
+    }
+
+    /// Refits tree to moved objects. Rebuilds it if the set of objects changed
+    /// or refitted boxes became too loose.
+    pub fn update(&mut self, boxes: &[Option<Aabb>]) {
+        if self.refit(boxes).is_err() || self.total_area() > REBUILD_AREA_RATIO * self.built_area {
+            *self = Bvh::new(boxes);
+        }
+    }
+
+    /// Returns index and distance of the nearest object hit by `ray` which is not behind the ray point.
+    /// `intersect` returns intersection distance of ray with object of given index.
+    ///
+    /// Nodes are visited front to back and skipped when they are farther than the nearest hit.
+    pub fn nearest<F>(&self, ray: &Ray, mut intersect: F) -> Option<(usize, f32)>
+    where
+        F: FnMut(usize) -> Option<f32>,
+    {
+        let mut nearest: Option<(usize, f32)> = None;
+        let mut test = |i: usize, nearest: &mut Option<(usize, f32)>| {
+            if let Some(distance) = intersect(i) {
+                if distance >= 0.0 && nearest.is_none_or(|(_, best)| distance < best) {
+                    *nearest = Some((i, distance));
+                }
+            }
+        };
+        for i in &self.unbounded {
+            test(*i, &mut nearest);
+        }
+
+        let root = match self.nodes.first() {
+            None => return nearest,
+            Some(root) => root,
+        };
+        let limit = |nearest: &Option<(usize, f32)>| nearest.map_or(f32::INFINITY, |(_, best)| best);
+        let mut stack = match root.aabb.ray_intersection(ray, 0.0, limit(&nearest)) {
+            None => return nearest,
+            Some((enter, _)) => vec![(0, enter)],
+        };
+        while let Some((index, enter)) = stack.pop() {
+            if enter > limit(&nearest) {
+                continue;
+            }
+            match self.nodes[index].kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    for i in &self.objects[first..first + count] {
+                        test(*i, &mut nearest);
+                    }
+                }
+                BvhNodeKind::Inner { left, right } => {
+                    let hit = |child: usize| self.nodes[child]
+                        .aabb
+                        .ray_intersection(ray, 0.0, limit(&nearest))
+                        .map(|(enter, _)| (child, enter));
+                    match (hit(left), hit(right)) {
+                        (None, None) => {}
+                        (Some(child), None) | (None, Some(child)) => stack.push(child),
+                        // Nearer child is pushed last, so it is visited first.
+                        (Some(a), Some(b)) if a.1 <= b.1 => stack.extend([b, a]),
+                        (Some(a), Some(b)) => stack.extend([a, b]),
+                    }
+                }
+            }
+        }
+        nearest
+    }
+
+    /// Returns true if `ray` hits any object at distance in `[0, max_distance)`.
+    /// `intersect` returns intersection distance of ray with object of given index.
+    ///
+    /// Traversal stops at the first hit, so it is cheaper than `nearest` for occlusion tests.
+    pub fn any_hit<F>(&self, ray: &Ray, max_distance: f32, mut intersect: F) -> bool
+    where
+        F: FnMut(usize) -> Option<f32>,
+    {
+        let mut hits = |i: usize| intersect(i).is_some_and(|distance| 0.0 <= distance && distance < max_distance);
+        if self.unbounded.iter().any(|i| hits(*i)) {
+            return true;
+        }
+        let mut stack = if self.nodes.is_empty() { vec![] } else { vec![0] };
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.aabb.ray_intersection(ray, 0.0, max_distance).is_none() {
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    if self.objects[first..first + count].iter().any(|i| hits(*i)) {
+                        return true;
+                    }
+                }
+                BvhNodeKind::Inner { left, right } => stack.extend([left, right]),
+            }
+        }
+        false
+    }
+}
+
+
+#[cfg(test)]
+mod bvh_tests {
+    use crate::utils::assert_eq_f32;
+
+    use super::*;
+
+    /// Spheres of radius 0.4 in nodes of `n`×`n`×`n` grid with step 1.
+    fn spheres(n: usize) -> Vec<Vector<f32>> {
+        let mut centers = vec![];
+        for x in 0..n {
+            for y in 0..n {
+                for z in 0..n {
+                    centers.push(Vector::from_xyz(x as f32, y as f32, z as f32));
+                }
+            }
+        }
+        centers
+    }
+
+    fn sphere_distance(center: &Vector<f32>, ray: &Ray) -> Option<f32> {
+        let offset = (&ray.point - center).unwrap();
+        let a = ray.direction.square_magnitude();
+        let b = 2.0 * offset.dot_product(&ray.direction);
+        let c = offset.square_magnitude() - 0.16;
+        let (t1, t2) = crate::utils::solve_quadratic(a, b, c)?;
+        [t1, t2].into_iter().find(|t| *t >= 0.0)
+    }
+
+    fn brute_force(centers: &[Vector<f32>], ray: &Ray) -> Option<(usize, f32)> {
+        (0..centers.len())
+            .filter_map(|i| sphere_distance(&centers[i], ray).map(|d| (i, d)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    fn boxes(centers: &[Vector<f32>]) -> Vec<Option<Aabb>> {
+        centers.iter().map(|center| Some(Aabb::around(center, 0.4))).collect()
+    }
+
+    fn rays() -> Vec<Ray> {
+        let mut rays = vec![];
+        for k in 0..50 {
+            let k = k as f32;
+            let point = Vector::from_xyz(-3.0 + 0.1 * k, -2.0 + 0.07 * k, 6.0 - 0.13 * k);
+            let direction = Vector::from_xyz(1.0, 0.3 + 0.02 * k, -0.2 - 0.01 * k);
+            rays.push(Ray::new(point, direction));
+        }
+        rays
+    }
+
+    #[test]
+    fn same_as_brute_force() {
+        let centers = spheres(5);
+        let bvh = Bvh::new(&boxes(&centers));
+        assert!(bvh.unbounded().is_empty());
+        for ray in rays() {
+            assert_eq!(bvh.nearest(&ray, |i| sphere_distance(&centers[i], &ray)), brute_force(&centers, &ray));
+        }
+    }
+
+    #[test]
+    fn refit_moved_objects() {
+        let mut centers = spheres(4);
+        let mut bvh = Bvh::new(&boxes(&centers));
+        let nodes_count = bvh.nodes_count();
+        for center in &mut centers {
+            center[2] += 0.5;
+        }
+        bvh.refit(&boxes(&centers)).unwrap();
+        assert_eq!(bvh.nodes_count(), nodes_count);
+        let root_box = bvh.root_box().unwrap();
+        assert!(root_box.min.approximately_equal(&Vector::from_xyz(-0.4, -0.4, 0.1), 1e-5));
+        assert!(root_box.max.approximately_equal(&Vector::from_xyz(3.4, 3.4, 3.9), 1e-5));
+        for ray in rays() {
+            assert_eq!(bvh.nearest(&ray, |i| sphere_distance(&centers[i], &ray)), brute_force(&centers, &ray));
+        }
+    }
+
+    #[test]
+    fn update_rebuilds_changed_scene() {
+        let centers = spheres(3);
+        let mut bvh = Bvh::new(&boxes(&centers[..10]));
+        assert!(bvh.refit(&boxes(&centers)).is_err());
+        bvh.update(&boxes(&centers));
+        for ray in rays() {
+            assert_eq!(bvh.nearest(&ray, |i| sphere_distance(&centers[i], &ray)), brute_force(&centers, &ray));
+        }
+    }
+
+    #[test]
+    fn unbounded_objects() {
+        let centers = spheres(2);
+        let mut boxes = boxes(&centers);
+        boxes.push(None);
+        let bvh = Bvh::new(&boxes);
+        assert_eq!(bvh.unbounded(), &[8]);
+        let ray = Ray::new(Vector::from_xyz(-5.0, 0.0, 0.0), Vector::from_xyz(1.0, 0.0, 0.0));
+        // Unbounded object is nearer than every sphere.
+        let hit = bvh.nearest(&ray, |i| if i == 8 { Some(1.0) } else { sphere_distance(&centers[i], &ray) });
+        assert_eq!(hit, Some((8, 1.0)));
+        // Objects behind the ray point are ignored.
+        let (i, distance) = bvh.nearest(&ray, |i| if i == 8 { Some(-1.0) } else { sphere_distance(&centers[i], &ray) }).unwrap();
+        assert_eq!(i, 0);
+        assert_eq_f32(distance, 4.6);
+    }
+
+    #[test]
+    fn empty() {
+        let bvh = Bvh::new(&[]);
+        let ray = Ray::new(Vector::zero3(), Vector::from_xyz(1.0, 0.0, 0.0));
+        assert_eq!(bvh.nearest(&ray, |_| Some(1.0)), None);
+        let mut bvh = Bvh::new(&[None]);
+        assert!(bvh.refit(&[Some(Aabb::around(&Vector::zero3(), 1.0))]).is_err());
+    }
+
+    #[test]
+    fn any_hit() {
+        let centers = spheres(3);
+        let bvh = Bvh::new(&boxes(&centers));
+        for ray in rays() {
+            let nearest = brute_force(&centers, &ray).map(|(_, distance)| distance);
+            let intersect = |i| sphere_distance(&centers[i], &ray);
+            assert_eq!(bvh.any_hit(&ray, f32::INFINITY, intersect), nearest.is_some());
+            if let Some(distance) = nearest {
+                assert!(bvh.any_hit(&ray, distance + 1e-3, intersect));
+                assert!(!bvh.any_hit(&ray, distance, intersect));
+            }
+        }
+    }
+}
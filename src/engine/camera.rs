@@ -1,3 +1,5 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
 use super::*;
 use crate::math::*;
 use crate::utils::vertical_fov_from_horizontal;
@@ -6,42 +8,268 @@ use crate::utils::vertical_fov_from_horizontal;
 #[derive(Clone, Debug)]
 pub struct Camera {
     pub transform: Transform,
+    /// Orthonormal frame of camera. `base[0]` is the view direction,
+    /// `base[1]` points to the left side of the screen and `base[2]` points up.
+    /// In 4d and higher `base[3]` is normal to the rendered 3d hyperslice.
+    pub frame: CoordinateSystem,
+    /// Offset of the rendered 3d hyperslice from camera position along `frame.base[3]`.
+    /// Not used in 3d.
+    pub hyperslice_offset: f32,
     pub horizontal_fov: f32,
     pub vertical_fov: f32,
+    /// Ratio of the screen width to its height in pixels of terminal.
+    pub aspect_ratio: f32,
     pub draw_distance: f32,
+    pub projection: Projection,
 }
 
 impl Camera {
     pub fn new(transform: Transform, config: &GameConfig) -> Camera {
+        let frame = CoordinateSystem::from_direction(
+            transform.position.clone(),
+            transform.get_direction()
+        ).expect("Transform direction is normalized.");
         Camera { 
             transform,
+            frame,
+            hyperslice_offset: 0.0,
             horizontal_fov: config.camera_fov,
-            vertical_fov: vertical_fov_from_horizontal(
-                config.camera_fov,
-                config.screen_width as f32 / config.screen_height as f32),
+            vertical_fov: vertical_fov_from_horizontal(config.camera_fov, config.screen_aspect_ratio()),
+            aspect_ratio: config.screen_aspect_ratio(),
             draw_distance: config.camera_draw_distance,
+            projection: config.camera_projection,
+        }
+    }
+
+    /// Creates camera with orientation given by `frame`. Camera is placed in `frame.initial_point`.
+    /// Frame will be orthonormalized. 
+    /// If frame has less than 3 linearly independent base vectors returns `None`.
+    pub fn new_in_frame(frame: CoordinateSystem, config: &GameConfig) -> Option<Camera> {
+        let mut frame = frame;
+        frame.orthonormalize();
+        if frame.base.len() < 3 || frame.base.len() != frame.dim() {
+            return None;
         }
+        let transform = Transform::new(frame.initial_point.clone(), &frame.base[0])?;
+        let mut camera = Camera::new(transform, config);
+        camera.frame = frame;
+        Some(camera)
+    }
+
+    /// Fits camera to the screen with ratio of width to height `aspect_ratio` in pixels of terminal.
+    /// Horizontal field of view is kept and vertical one is recomputed.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.aspect_ratio = aspect_ratio;
+        self.vertical_fov = vertical_fov_from_horizontal(self.horizontal_fov, aspect_ratio);
     }
 
-    pub fn get_rays_matrix(&self, transform: &Transform, n: usize, m: usize) -> Matrix<Ray> {
-        // TODO: if fov >= pi than log warning message.
+    /// Rotates camera in the plane spanned by `frame.base[a]` and `frame.base[b]`.
+    /// In 4d there are six such planes.
+    pub fn rotate(&mut self, a: usize, b: usize, radians: f32) -> Result<(), ()> {
+        self.frame.rotate(a, b, radians)?;
+        self.frame.orthonormalize();
+        self.transform.set_direction(&self.frame.base[0])
+    }
+
+    /// Moves camera to `position` keeping its orientation.
+    pub fn set_position(&mut self, position: Vector<f32>) {
+        self.frame.initial_point = position.clone();
+        self.transform.position = position;
+    }
 
-        let delta_alpha: f32 = self.horizontal_fov / n as f32;
-        let delta_beta: f32 = self.vertical_fov / m as f32;
-        let alpha_i = |i| delta_alpha * i as f32 - 0.5 * delta_alpha;
-        let beta_j = |j| delta_beta * j as f32 - 0.5 * delta_beta;
-        // View direction vector.
-        let v: &Vector<f32> = &transform.get_direction();
-        let v_ij = |i, j| v.rotate3d(0.0, beta_j(j), alpha_i(i)).unwrap();
-        // Fix "fish eye" effect.
-        let v_fixed_ij = |i, j| {
-            let v_ij = v_ij(i, j);
-            v.square_magnitude() / v.dot_product(&v_ij) * &v_ij
-        };
+    /// Returns origin of rays: camera position shifted by `hyperslice_offset` along `frame.base[3]`.
+    /// In 3d it is just camera position.
+    pub fn get_hyperslice_origin(&self) -> Vector<f32> {
+        match self.frame.base.get(3) {
+            None => self.frame.initial_point.clone(),
+            Some(normal) => (&self.frame.initial_point + &(normal * self.hyperslice_offset)).unwrap(),
+        }
+    }
 
+    /// Returns region of space visible by camera.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_camera(self)
+    }
+
+    /// Returns rays which lie in the 3d hyperslice spanned by first three vectors of `frame`.
+    /// Rays go through centres of pixels, see `get_hyperslice_ray`.
+    /// Row `i` of matrix goes from the top of the screen to the bottom,
+    /// column `j` goes from the left to the right.
+    pub fn get_hyperslice_rays_matrix(&self, n: usize, m: usize) -> Matrix<Ray> {
         Matrix::<Ray>::from_rule(
             n, m,
-            |i, j| Ray::new(transform.position.clone(), v_fixed_ij(i, j))
+            |i, j| self.get_hyperslice_ray((j as f32 + 0.5) / m as f32, (i as f32 + 0.5) / n as f32)
         )
     }
-}
\ No newline at end of file
+
+    /// Returns ray through point `(u, v)` of the screen in the 3d hyperslice spanned by first three vectors of `frame`.
+    /// `u` goes from 0 on the left side of the screen to 1 on the right one,
+    /// `v` goes from 0 on the top to 1 on the bottom.
+    /// Rays are spread over the screen by camera `projection`.
+    /// Perspective rays go through points of image plane at distance 1 in front of camera
+    /// and orthographic rays have unit length, so ray parameter of hit is its depth.
+    /// Fisheye and equirectangular rays are unit vectors.
+    pub fn get_hyperslice_ray(&self, u: f32, v: f32) -> Ray {
+        let origin = self.get_hyperslice_origin();
+        // Coordinates from -1 to 1, positive to the left and up.
+        let (x, y) = (1.0 - 2.0 * u, 1.0 - 2.0 * v);
+        let (forward, left, up) = (&self.frame.base[0], &self.frame.base[1], &self.frame.base[2]);
+        // Vector with coordinates `(a, b, c)` in camera frame.
+        let in_frame = |a: f32, b: f32, c: f32| ((forward * a + left * b).unwrap() + up * c).unwrap();
+
+        match self.projection {
+            Projection::Perspective => {
+                let half_width = (0.5 * self.horizontal_fov).tan();
+                let half_height = (0.5 * self.vertical_fov).tan();
+                Ray::new(origin, in_frame(1.0, x * half_width, y * half_height))
+            }
+            Projection::Orthographic { width } => {
+                let (half_width, half_height) = (0.5 * width, 0.5 * width / self.aspect_ratio);
+                let point = (&origin + &in_frame(0.0, x * half_width, y * half_height)).unwrap();
+                Ray::new(point, forward.clone())
+            }
+            Projection::Fisheye => {
+                // Angles to the left and up are proportional to distance from the centre of the screen.
+                let half_fov = 0.5 * self.horizontal_fov;
+                let (left_angle, up_angle) = (x * half_fov, y * half_fov / self.aspect_ratio);
+                let angle = left_angle.hypot(up_angle);
+                let direction = if angle == 0.0 {
+                    forward.clone()
+                } else {
+                    let sine = angle.sin() / angle;
+                    in_frame(angle.cos(), left_angle * sine, up_angle * sine)
+                };
+                Ray::new(origin, direction)
+            }
+            Projection::Equirectangular => {
+                let (longitude, latitude) = (x * PI, y * FRAC_PI_2);
+                let direction = in_frame(
+                    latitude.cos() * longitude.cos(),
+                    latitude.cos() * longitude.sin(),
+                    latitude.sin(),
+                );
+                Ray::new(origin, direction)
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod camera_tests {
+    use super::*;
+
+    fn config() -> GameConfig {
+        GameConfig {
+            camera_fov: std::f32::consts::PI / 2.0,
+            ..GameConfig::default()
+        }
+    }
+
+    #[test]
+    fn new_in_frame4d() {
+        let camera = Camera::new_in_frame(CoordinateSystem::identity(4), &config()).unwrap();
+        assert_eq!(camera.transform.dim(), 4);
+        assert_eq!(camera.transform.get_direction(), &Vector::new(vec![1.0, 0.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn new_in_frame2d() {
+        assert!(Camera::new_in_frame(CoordinateSystem::identity(2), &config()).is_none());
+    }
+
+    #[test]
+    fn hyperslice_rays_lie_in_slice() {
+        let mut camera = Camera::new_in_frame(CoordinateSystem::identity(4), &config()).unwrap();
+        camera.hyperslice_offset = 2.0;
+        let rays = camera.get_hyperslice_rays_matrix(3, 5);
+        for i in 0..3 {
+            for j in 0..5 {
+                assert_eq!(rays[i][j].point, Vector::new(vec![0.0, 0.0, 0.0, 2.0]));
+                assert_eq!(rays[i][j].direction[3], 0.0);
+            }
+        }
+        // Central ray looks forward.
+        assert!(rays[1][2].direction.approximately_equal(&Vector::new(vec![1.0, 0.0, 0.0, 0.0]), 1e-6));
+        // Top left ray looks to the left and up.
+        assert!(rays[0][0].direction[1] > 0.0 && rays[0][0].direction[2] > 0.0);
+    }
+
+    #[test]
+    fn rotate_xw_moves_slice() {
+        let mut camera = Camera::new_in_frame(CoordinateSystem::identity(4), &config()).unwrap();
+        camera.rotate(0, 3, std::f32::consts::PI / 2.0).unwrap();
+        assert!(camera.transform.get_direction()
+            .approximately_equal(&Vector::new(vec![0.0, 0.0, 0.0, 1.0]), 1e-6));
+        assert!(camera.frame.is_orthonormal(1e-5));
+    }
+
+    #[test]
+    fn vertical_fov_of_tall_cells() {
+        // 40×20 cells of 1×2 pixels make a square screen.
+        let config = GameConfig { screen_width: 40, screen_height: 20, cell_aspect_ratio: 0.5, ..config() };
+        let camera = Camera::new(Transform::default(), &config);
+        assert!((camera.vertical_fov - std::f32::consts::PI / 2.0).abs() < 1e-6);
+        // Square cells make the screen twice as wide as tall.
+        let config = GameConfig { cell_aspect_ratio: 1.0, ..config };
+        let camera = Camera::new(Transform::default(), &config);
+        assert!((camera.vertical_fov - 2.0 * 0.5_f32.atan()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn centred_perspective_rays() {
+        let camera = Camera::new(Transform::default(), &config());
+        let rays = camera.get_hyperslice_rays_matrix(4, 6);
+        // Rays of opposite corners are symmetric about the view direction.
+        let (top_left, bottom_right) = (&rays[0][0].direction, &rays[3][5].direction);
+        assert!((top_left[0] - bottom_right[0]).abs() < 1e-6);
+        assert!((top_left[1] + bottom_right[1]).abs() < 1e-6);
+        assert!((top_left[2] + bottom_right[2]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn orthographic_rays() {
+        let mut camera = Camera::new(Transform::default(), &config());
+        camera.aspect_ratio = 2.0;
+        camera.projection = Projection::Orthographic { width: 4.0 };
+        let rays = camera.get_hyperslice_rays_matrix(2, 4);
+        for row in 0..2 {
+            for column in 0..4 {
+                assert_eq!(rays[row][column].direction, Vector::from_xyz(1.0, 0.0, 0.0));
+            }
+        }
+        assert_eq!(rays[0][0].point, Vector::from_xyz(0.0, 1.5, 0.5));
+        assert_eq!(rays[1][3].point, Vector::from_xyz(0.0, -1.5, -0.5));
+    }
+
+    #[test]
+    fn fisheye_rays() {
+        let mut camera = Camera::new(Transform::default(), &config());
+        camera.horizontal_fov = PI;
+        camera.aspect_ratio = 2.0;
+        camera.projection = Projection::Fisheye;
+        let rays = camera.get_hyperslice_rays_matrix(1, 4);
+        // Angle from view direction is proportional to distance from the centre: 3/4 and 1/4 of 90 degrees.
+        let angle = |direction: &Vector<f32>| direction[0].acos();
+        assert!((angle(&rays[0][0].direction) - 0.75 * FRAC_PI_2).abs() < 1e-5);
+        assert!((angle(&rays[0][1].direction) - 0.25 * FRAC_PI_2).abs() < 1e-5);
+        assert!(rays[0][0].direction[1] > 0.0 && rays[0][3].direction[1] < 0.0);
+        assert!((rays[0][0].direction.magnitude() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn equirectangular_rays() {
+        let mut camera = Camera::new(Transform::default(), &config());
+        camera.projection = Projection::Equirectangular;
+        let rays = camera.get_hyperslice_rays_matrix(2, 4);
+        // Columns look back-left, front-left, front-right and back-right, rows look up and down.
+        let expected = [(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)];
+        for (column, (x, y)) in expected.iter().enumerate() {
+            let direction = &rays[0][column].direction;
+            assert!(direction[0] * x > 0.0 && direction[1] * y > 0.0 && direction[2] > 0.0);
+            assert!(rays[1][column].direction[2] < 0.0);
+        }
+        let up = Vector::from_xyz(0.0, 0.0, 1.0);
+        assert!((rays[0][0].direction.dot_product(&up) - (0.5 * FRAC_PI_2).sin()).abs() < 1e-6);
+    }
+}
@@ -1,58 +1,452 @@
-use std::{cell::RefCell, rc::Rc};
-
 use super::*;
 use crate::math::*;
+use crate::utils::XorShift;
 
+/// Relative jump of inverse depth between neighbour pixels which makes an edge.
+/// Inverse depth changes linearly across flat surfaces, so they do not make edges.
+const EDGE_DEPTH_RATIO: f32 = 0.1;
+/// Neighbour pixels with normals at the larger angle than `acos(EDGE_NORMAL_COS)` lie across an edge.
+const EDGE_NORMAL_COS: f32 = 0.8;
+/// Relative difference of depth between neighbour pixels which makes adaptive supersampling cast more rays.
+const SUPERSAMPLING_DEPTH_RATIO: f32 = 0.1;
 
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
-    pub distances: Matrix<Option<f32>>
+    pub distances: Matrix<Option<f32>>,
+    /// Materials of objects hit by rays, `None` for misses.
+    pub materials: Matrix<Option<Material>>,
+    /// Unit normals at hit points facing the camera, `None` for misses.
+    pub normals: Matrix<Option<Vector<f32>>>,
+    /// Shaded colours of hit points, `None` for misses.
+    pub colors: Matrix<Option<Rgb>>,
+    /// Acceleration structure over objects of the last update which were not culled.
+    pub bvh: Bvh,
+    /// Acceleration structure over all objects of the last update for shadow, reflected
+    /// and refracted rays, because objects out of camera frustum can be seen in mirrors or cast shadows.
+    pub world_bvh: Bvh,
+    /// Number of objects passed to the last update.
+    pub objects_count: usize,
+    /// Number of objects culled by camera frustum in the last update.
+    pub culled_count: usize,
+    /// Number of shadow rays cast to every area light.
+    pub shadow_samples: usize,
+    /// Offset of shadow and secondary rays from surface along its normal.
+    pub shadow_bias: f32,
+    /// Maximal depth of reflected and refracted rays, 0 disables them.
+    pub max_bounces: usize,
+    /// Fog of primary and secondary rays.
+    pub fog: Fog,
+    /// Colour of pixel is averaged over `supersampling` × `supersampling` rays, 1 casts one ray through the centre.
+    pub supersampling: usize,
+    /// Rays of supersampling go through random points of their subpixels instead of centres.
+    pub jittered_sampling: bool,
+    /// Only pixels which depth differs strongly from their neighbours are supersampled.
+    pub adaptive_supersampling: bool,
+    /// Passes applied to colours in order after shading.
+    pub post_processes: Vec<PostProcess>,
 }
 
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Canvas {
+        let config = GameConfig::default();
         Canvas { 
             width,
             height,
-            distances: Matrix::from_rule(width, height, |_, _| None) 
+            distances: Matrix::from_rule(height, width, |_, _| None),
+            materials: Matrix::from_rule(height, width, |_, _| None),
+            normals: Matrix::from_rule(height, width, |_, _| None),
+            colors: Matrix::from_rule(height, width, |_, _| None),
+            bvh: Bvh::default(),
+            world_bvh: Bvh::default(),
+            objects_count: 0,
+            culled_count: 0,
+            shadow_samples: config.shadow_samples,
+            shadow_bias: config.shadow_bias,
+            max_bounces: config.max_bounces,
+            fog: Fog::default(),
+            supersampling: config.supersampling,
+            jittered_sampling: config.jittered_sampling,
+            adaptive_supersampling: config.adaptive_supersampling,
+            post_processes: config.post_processes,
         }
     }
 
+    /// Creates canvas with as many pixels as `config.render_mode` draws on the screen.
     pub fn new_from_game_config(config: &GameConfig) -> Canvas {
-        Canvas::new(config.screen_width, config.screen_height)
+        Canvas::new_for_cells(config.screen_width, config.screen_height, config)
     }
 
-    pub fn update<'a>(
-        &mut self,
-        camera: &Camera,
-        camera_transform: &Transform,
-        objects: Vec<(&Transform, &dyn GameObject)>
-    ) {
-        let rays = camera.get_rays_matrix(camera_transform, self.height, self.width);
+    /// Creates canvas with as many pixels as `config.render_mode` draws in `width` × `height` cells.
+    pub fn new_for_cells(width: usize, height: usize, config: &GameConfig) -> Canvas {
+        let (columns, rows) = config.render_mode.cell_pixels();
+        Canvas {
+            shadow_samples: config.shadow_samples,
+            shadow_bias: config.shadow_bias,
+            max_bounces: config.max_bounces,
+            fog: Fog::new_from_game_config(config),
+            supersampling: config.supersampling,
+            jittered_sampling: config.jittered_sampling,
+            adaptive_supersampling: config.adaptive_supersampling,
+            post_processes: config.post_processes.clone(),
+            ..Canvas::new(width * columns, height * rows)
+        }
+    }
+
+    /// Casts a ray for every pixel and stores distance to the nearest object, its material,
+    /// normal and colour lit by `lights`. Scene without lights is lit by white ambient light.
+    /// Reflective and transparent materials spawn secondary rays up to `max_bounces` deep.
+    /// Surfaces farther than positive `camera.draw_distance` are clipped and nearer ones fade in `fog`.
+    /// With `supersampling` colours are averaged over several rays per pixel, misses count as black,
+    /// so pixels on silhouettes may have colour without material. Other data is of the central ray.
+    /// Finally colours are changed by `post_processes` in order.
+    /// Objects which are certainly out of camera frustum are culled before casting rays,
+    /// but they still cast shadows and are seen in reflections.
+    pub fn update(&mut self, camera: &Camera, objects: &[&SceneObject], lights: &[&Light]) {
+        // Cameras in 4d and higher render a 3d hyperslice of the world.
+        let rays = camera.get_hyperslice_rays_matrix(self.height, self.width);
 
         debug_assert_eq!(rays.cols_count(), self.width);
         debug_assert_eq!(rays.rows_count(), self.height);
 
-        let min_distance = 
-        |objects: &Vec<(&Transform, &dyn GameObject)>, ray: &Ray| {
-            let mut min_distance: Option<f32> = None;
-            for (transform, game_object) in objects {
-                let distance = match game_object.intersection_distance(&transform, ray) {
-                    None => continue,
-                    Some(distance) => distance,
-                };
-                if min_distance.is_none() || distance < min_distance.unwrap() {
-                    min_distance = Some(distance);
+        self.objects_count = objects.len();
+        let all_boxes: Vec<Option<Aabb>> = objects
+            .iter()
+            .map(|object| object.game_object.bounding_box(&object.transform))
+            .collect();
+        let frustum = camera.frustum();
+        let (visible, boxes): (Vec<&SceneObject>, Vec<Option<Aabb>>) = objects
+            .iter()
+            .zip(&all_boxes)
+            .filter(|(_, aabb)| aabb.as_ref().is_none_or(|aabb| frustum.intersects(aabb)))
+            .map(|(object, aabb)| (*object, aabb.clone()))
+            .unzip();
+        self.culled_count = self.objects_count - visible.len();
+
+        // Trees are refitted every frame, because objects could move since the last one.
+        self.bvh.update(&boxes);
+        self.world_bvh.update(&all_boxes);
+
+        let bvh = &self.bvh;
+        // Returns index of the nearest visible object hit by primary ray and distance to it.
+        let cast = |ray: &Ray| {
+            bvh.nearest(ray, |k| visible[k].game_object.intersection_distance(&visible[k].transform, ray))
+                .filter(|(_, distance)| {
+                    camera.draw_distance <= 0.0 || distance * ray.direction.magnitude() <= camera.draw_distance
+                })
+        };
+        let hits = Matrix::from_rule(self.height, self.width, |i, j| cast(&rays[i][j]));
+        let surfaces = Matrix::from_rule(
+            self.height, self.width,
+            |i, j| hits[i][j].map(|(k, distance)| (k, Surface::new(visible[k], &rays[i][j], distance)))
+        );
+        self.distances = Matrix::from_rule(
+            self.height, self.width,
+            |i, j| hits[i][j].map(|(_, distance)| distance)
+        );
+        self.materials = Matrix::from_rule(
+            self.height, self.width,
+            |i, j| hits[i][j].map(|(k, _)| visible[k].material.clone())
+        );
+        self.normals = Matrix::from_rule(
+            self.height, self.width,
+            |i, j| surfaces[i][j].as_ref().map(|(_, surface)| surface.normal.clone())
+        );
+
+        let scene = Scene {
+            objects,
+            world_bvh: &self.world_bvh,
+            lights,
+            ambient: if lights.is_empty() {
+                Rgb::WHITE
+            } else {
+                lights.iter().fold(Rgb::BLACK, |sum, light| sum + light.ambient_radiance())
+            },
+            shadow_samples: self.shadow_samples,
+            shadow_bias: self.shadow_bias,
+            max_bounces: self.max_bounces,
+            fog: self.fog,
+        };
+        // Returns colour of primary `ray` hitting `k`-th visible object at `distance`.
+        let shade = |ray: &Ray, k: usize, distance: f32, surface: &Surface, rng: &mut XorShift| {
+            let color = scene.shade_surface(&visible[k].material, ray, surface, 0, rng);
+            scene.fog.apply(color, distance * ray.direction.magnitude())
+        };
+        let (width, height) = (self.width, self.height);
+        let samples = self.supersampling.max(1);
+        let supersampled = Matrix::from_rule(
+            height, width,
+            |i, j| samples > 1 && (!self.adaptive_supersampling || self.depth_differs(i, j))
+        );
+        let jittered = self.jittered_sampling;
+        self.colors = Matrix::from_rule(
+            height, width,
+            |i, j| {
+                // Generator is seeded by pixel, so soft shadows do not flicker between frames.
+                let mut rng = XorShift::new((i * width + j) as u64);
+                if !supersampled[i][j] {
+                    let (k, surface) = surfaces[i][j].as_ref()?;
+                    return Some(shade(&rays[i][j], *k, hits[i][j]?.1, surface, &mut rng));
+                }
+                // Every ray goes through its own cell of `samples` × `samples` grid over the pixel.
+                let mut sum = Rgb::BLACK;
+                let mut hits_count = 0;
+                for row in 0..samples {
+                    for column in 0..samples {
+                        let (du, dv) = if jittered { (rng.next_f32(), rng.next_f32()) } else { (0.5, 0.5) };
+                        let u = (j as f32 + (column as f32 + du) / samples as f32) / width as f32;
+                        let v = (i as f32 + (row as f32 + dv) / samples as f32) / height as f32;
+                        let ray = camera.get_hyperslice_ray(u, v);
+                        if let Some((k, distance)) = cast(&ray) {
+                            let surface = Surface::new(visible[k], &ray, distance);
+                            sum += shade(&ray, k, distance, &surface, &mut rng);
+                            hits_count += 1;
+                        }
+                    }
+                }
+                match hits_count {
+                    0 => None,
+                    _ => Some(sum * (1.0 / (samples * samples) as f32)),
+                }
+            }
+        );
+        for pass in &self.post_processes {
+            self.colors = pass.apply(&self.distances, &self.colors);
+        }
+    }
+
+    /// Returns true if pixel is a hit and one of its neighbours is a miss or vice versa,
+    /// or if depths of pixel and its neighbour differ by more than `SUPERSAMPLING_DEPTH_RATIO`.
+    fn depth_differs(&self, i: usize, j: usize) -> bool {
+        let neighbours = [(i.wrapping_sub(1), j), (i + 1, j), (i, j.wrapping_sub(1)), (i, j + 1)];
+        neighbours
+            .iter()
+            .filter(|(k, l)| *k < self.height && *l < self.width)
+            .any(|(k, l)| match (self.distances[i][j], self.distances[*k][*l]) {
+                (Some(a), Some(b)) => (a - b).abs() > SUPERSAMPLING_DEPTH_RATIO * a.min(b),
+                (None, None) => false,
+                _ => true,
+            })
+    }
+
+    /// Returns true if pixel lies on an edge of object: on its silhouette,
+    /// on a crease where normal turns sharply or where depth jumps.
+    /// Of two pixels across silhouette only the nearer one is on the edge.
+    pub fn is_edge(&self, i: usize, j: usize) -> bool {
+        let (distance, normal) = match (self.distances[i][j], &self.normals[i][j]) {
+            (Some(distance), Some(normal)) => (distance, normal),
+            _ => return false,
+        };
+        let neighbour = |i: Option<usize>, j: Option<usize>| match (i, j) {
+            (Some(i), Some(j)) if i < self.height && j < self.width => Some((self.distances[i][j], &self.normals[i][j])),
+            _ => None,
+        };
+        let axes = [
+            (neighbour(i.checked_sub(1), Some(j)), neighbour(Some(i + 1), Some(j))),
+            (neighbour(Some(i), j.checked_sub(1)), neighbour(Some(i), Some(j + 1))),
+        ];
+        for (before, after) in axes {
+            for (other_distance, other_normal) in [before, after].into_iter().flatten() {
+                match (other_distance, other_normal) {
+                    (Some(other_distance), Some(other_normal)) => {
+                        let behind = distance > other_distance * (1.0 + EDGE_DEPTH_RATIO);
+                        if !behind && normal.dot_product(other_normal) < EDGE_NORMAL_COS {
+                            return true;
+                        }
+                    }
+                    _ => return true,
                 }
             }
-            min_distance
+            if let (Some((Some(a), _)), Some((Some(b), _))) = (before, after) {
+                let behind = distance > f32::min(a, b) * (1.0 + EDGE_DEPTH_RATIO);
+                if !behind && (1.0 / a + 1.0 / b - 2.0 / distance).abs() > EDGE_DEPTH_RATIO / distance {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Point where a ray hits an object.
+#[derive(Clone)]
+struct Surface {
+    point: Vector<f32>,
+    /// Unit normal turned to the ray origin.
+    normal: Vector<f32>,
+    /// True if ray hits the outer side of the surface, i.e. enters the object.
+    front: bool,
+    /// Texture coordinates if the object knows them.
+    uv: Option<(f32, f32)>,
+}
+
+impl Surface {
+    /// If the object does not know its normal, the surface is assumed to face the ray.
+    fn new(object: &SceneObject, ray: &Ray, distance: f32) -> Surface {
+        let point = ray.point_at(distance);
+        let mut view = -ray.direction.clone();
+        view.normalize();
+        let (normal, front) = match object.game_object.normal(&object.transform, &point) {
+            Some(normal) if normal.dot_product(&view) < 0.0 => (-normal, false),
+            Some(normal) => (normal, true),
+            None => (view, true),
         };
+        let uv = object.game_object.uv(&object.transform, &point);
+        Surface { point, normal, front, uv }
+    }
+}
 
-        self.distances = Matrix::from_rule(
-            self.height, self.width,
-            |i, j| min_distance(&objects, &rays[i][j])
-        )
+/// Reflects unit `direction` of ray by surface with unit `normal`.
+fn reflect(direction: &Vector<f32>, normal: &Vector<f32>) -> Vector<f32> {
+    (direction - &(normal * (2.0 * direction.dot_product(normal)))).unwrap()
+}
+
+/// Refracts unit `direction` of ray going through surface with unit `normal` turned against the ray.
+/// `eta` is the ratio of refractive indices of the media before and after surface.
+/// Returns `None` in case of total internal reflection.
+fn refract(direction: &Vector<f32>, normal: &Vector<f32>, eta: f32) -> Option<Vector<f32>> {
+    let cosine = -direction.dot_product(normal);
+    let k = 1.0 - eta * eta * (1.0 - cosine * cosine);
+    if k < 0.0 {
+        return None;
+    }
+    &(direction * eta) + &(normal * (eta * cosine - k.sqrt()))
+}
+
+/// Schlick's approximation of the part of light reflected by the border of media
+/// with refractive indices `n1` and `n2`, `cosine` is cosine of the angle of incidence.
+fn schlick(cosine: f32, n1: f32, n2: f32) -> f32 {
+    let r0 = ((n1 - n2) / (n1 + n2)).powi(2);
+    r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+}
+
+/// Objects and lights of `Canvas::update` used for shading.
+struct Scene<'a> {
+    objects: &'a [&'a SceneObject],
+    world_bvh: &'a Bvh,
+    lights: &'a [&'a Light],
+    ambient: Rgb,
+    shadow_samples: usize,
+    shadow_bias: f32,
+    max_bounces: usize,
+    fog: Fog,
+}
+
+impl Scene<'_> {
+    /// Returns colour seen along secondary `ray` through fog, black if it hits nothing.
+    fn trace(&self, ray: &Ray, depth: usize, rng: &mut XorShift) -> Rgb {
+        let objects = self.objects;
+        let hit = self.world_bvh.nearest(ray, |k| {
+            objects[k].game_object.intersection_distance(&objects[k].transform, ray)
+        });
+        match hit {
+            None => Rgb::BLACK,
+            Some((k, distance)) => {
+                let surface = Surface::new(objects[k], ray, distance);
+                let color = self.shade_surface(&objects[k].material, ray, &surface, depth, rng);
+                self.fog.apply(color, distance * ray.direction.magnitude())
+            }
+        }
+    }
+
+    /// Returns colour of `surface` hit by `ray` which is `depth` bounces from camera.
+    /// Local Phong colour is blended with reflected and refracted light by Fresnel term.
+    fn shade_surface(&self, material: &Material, ray: &Ray, surface: &Surface, depth: usize, rng: &mut XorShift) -> Rgb {
+        let mut direction = ray.direction.clone();
+        direction.normalize();
+        let view = -direction.clone();
+        let local = self.shade(material, surface, &view, rng);
+        let (reflectivity, transparency) = (material.reflectivity, material.transparency);
+        if depth >= self.max_bounces || (reflectivity <= 0.0 && transparency <= 0.0) {
+            return local;
+        }
+
+        let (n1, n2) = if surface.front { (1.0, material.refractive_index) } else { (material.refractive_index, 1.0) };
+        let cosine = surface.normal.dot_product(&view).clamp(0.0, 1.0);
+        let refracted = if transparency > 0.0 { refract(&direction, &surface.normal, n1 / n2) } else { None };
+        // Without refracted ray all light passing the border is reflected.
+        let fresnel = if refracted.is_some() { schlick(cosine, n1, n2) } else { 1.0 };
+        let reflected_weight = reflectivity + transparency * fresnel;
+        let refracted_weight = transparency * (1.0 - fresnel);
+
+        let mut color = local * (1.0 - reflectivity - transparency).max(0.0);
+        if reflected_weight > 0.0 {
+            let origin = (&surface.point + &(&surface.normal * self.shadow_bias)).unwrap();
+            let reflected = Ray::new(origin, reflect(&direction, &surface.normal));
+            color += self.trace(&reflected, depth + 1, rng) * reflected_weight;
+        }
+        if let Some(refracted) = refracted.filter(|_| refracted_weight > 0.0) {
+            let origin = (&surface.point - &(&surface.normal * self.shadow_bias)).unwrap();
+            color += self.trace(&Ray::new(origin, refracted), depth + 1, rng) * refracted_weight;
+        }
+        color
+    }
+
+    /// Returns colour of surface point by Phong reflection model:
+    /// emission, ambient light, and diffuse and specular terms of every light source
+    /// reduced by shadows. `view` points from the surface to the viewer.
+    /// Textured material gives diffuse colour at texture coordinates of the surface.
+    fn shade(&self, material: &Material, surface: &Surface, view: &Vector<f32>, rng: &mut XorShift) -> Rgb {
+        let (point, normal) = (&surface.point, &surface.normal);
+        let mut view = view.clone();
+        view.normalize();
+        let albedo = material.color_at(surface.uv);
+        let mut color = material.emissive + albedo * self.ambient;
+        for light in self.lights {
+            let sample = match light.sample(point) {
+                None => continue,
+                Some(sample) => sample,
+            };
+            let diffuse = normal.dot_product(&sample.direction);
+            if diffuse <= 0.0 {
+                continue;
+            }
+            let visibility = self.visibility(light, &sample, point, normal, rng);
+            if visibility == 0.0 {
+                continue;
+            }
+            let radiance = sample.radiance * visibility;
+            color += albedo * radiance * diffuse;
+            if material.specular > 0.0 {
+                // Direction of light reflected by the surface.
+                let reflected = (&(normal * (2.0 * diffuse)) - &sample.direction).unwrap();
+                let specular = reflected.dot_product(&view).max(0.0).powf(material.shininess);
+                color += radiance * (material.specular * specular);
+            }
+        }
+        color
+    }
+
+    /// Returns part of `light` which reaches `point` not blocked by objects, in `[0, 1]`.
+    /// Area lights are sampled in `shadow_samples` jittered points, which makes soft shadows.
+    fn visibility(&self, light: &Light, sample: &LightSample, point: &Vector<f32>, normal: &Vector<f32>, rng: &mut XorShift) -> f32 {
+        // Shadow rays start a bit above the surface, otherwise they could hit it due to rounding.
+        let origin = (point + &(normal * self.shadow_bias)).unwrap();
+        let samples = if light.radius > 0.0 { self.shadow_samples.max(1) } else { 1 };
+        let visible = (0..samples)
+            .filter(|_| {
+                let jitter = if light.radius > 0.0 {
+                    &rng.in_unit_ball(point.dim()) * light.radius
+                } else {
+                    Vector::zero(point.dim())
+                };
+                // Shadow ray reaches point light at parameter 1.
+                let (target, max_distance) = match light.position() {
+                    Some(position) => ((&(position + &jitter).unwrap() - &origin).unwrap(), 1.0),
+                    None => ((&sample.direction + &jitter).unwrap(), f32::INFINITY),
+                };
+                !self.occluded(&Ray::new(origin.clone(), target), max_distance)
+            })
+            .count();
+        visible as f32 / samples as f32
+    }
+
+    /// Returns true if `ray` hits any object at parameter in `[0, max_distance)`.
+    fn occluded(&self, ray: &Ray, max_distance: f32) -> bool {
+        let objects = self.objects;
+        self.world_bvh.any_hit(ray, max_distance, |k| {
+            objects[k].game_object.intersection_distance(&objects[k].transform, ray)
+        })
     }
 }
 
@@ -76,5 +470,343 @@ mod canvas_tests {
 
         // println!("{:?}", canvas.distances);
     }
-}
 
+    fn brute_force(objects: &[&SceneObject], ray: &Ray) -> Option<f32> {
+        objects
+            .iter()
+            .filter_map(|object| object.game_object.intersection_distance(&object.transform, ray))
+            .filter(|distance| *distance >= 0.0)
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    fn sphere(x: f32, y: f32, z: f32, r: f32) -> SceneObject {
+        SceneObject::new(
+            Transform::new_from_coords(x, y, z, 1.0, 0.0, 0.0).unwrap(),
+            Box::new(Hyperellipsoid::new3d(r, r, r)),
+        )
+    }
+
+    fn plane(x: f32, y: f32, z: f32, normal_x: f32, normal_y: f32, normal_z: f32) -> SceneObject {
+        SceneObject::new(
+            Transform::new_from_coords(x, y, z, normal_x, normal_y, normal_z).unwrap(),
+            Box::new(Hyperplane),
+        )
+    }
+
+    /// Camera with square pixels on square canvas.
+    fn camera() -> Camera {
+        let config = GameConfig {
+            camera_fov: std::f32::consts::PI / 2.0,
+            screen_width: 20,
+            screen_height: 10,
+            cell_aspect_ratio: 0.5,
+            ..GameConfig::default()
+        };
+        Camera::new(Transform::default(), &config)
+    }
+
+    #[test]
+    fn same_as_brute_force() {
+        let camera = camera();
+        let mut scene = vec![];
+        for x in 0..6 {
+            for y in -3..3 {
+                for z in -2..2 {
+                    scene.push(sphere(3.0 + x as f32, y as f32, z as f32, 0.5));
+                }
+            }
+        }
+        scene.push(plane(0.0, 0.0, -3.0, 0.0, 0.0, 1.0));
+        let objects: Vec<&SceneObject> = scene.iter().collect();
+
+        let mut canvas = Canvas::new(20, 10);
+        canvas.update(&camera, &objects, &[]);
+        let rays = camera.get_hyperslice_rays_matrix(10, 20);
+        for i in 0..10 {
+            for j in 0..20 {
+                assert_eq!(canvas.distances[i][j], brute_force(&objects, &rays[i][j]));
+            }
+        }
+    }
+
+    #[test]
+    fn plane_behind_camera() {
+        let behind = plane(-2.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+        let mut canvas = Canvas::new(3, 3);
+        canvas.update(&camera(), &[&behind], &[]);
+        assert_eq!(canvas.distances[1][1], None);
+    }
+
+    #[test]
+    fn culled_objects() {
+        let scene = [
+            sphere(5.0, 0.0, 0.0, 1.0),
+            sphere(-5.0, 0.0, 0.0, 1.0),
+            sphere(500.0, 0.0, 0.0, 1.0),
+            plane(0.0, 0.0, -3.0, 0.0, 0.0, 1.0),
+        ];
+        let mut canvas = Canvas::new(5, 5);
+        canvas.update(&camera(), &scene.iter().collect::<Vec<_>>(), &[]);
+        assert_eq!((canvas.culled_count, canvas.objects_count), (2, 4));
+        assert_eq!(canvas.distances[2][2], Some(4.0));
+    }
+
+    #[test]
+    fn hit_materials() {
+        let red = Material { glyph: Some('r'), ..Material::from_color(Rgb::new(1.0, 0.0, 0.0)) };
+        let mut near = sphere(3.0, 0.0, 0.0, 1.0);
+        near.material = red.clone();
+        let far = sphere(10.0, 0.0, 0.0, 5.0);
+        let mut canvas = Canvas::new(9, 9);
+        canvas.update(&camera(), &[&near, &far], &[]);
+        assert_eq!(canvas.materials[4][4], Some(red));
+        assert_eq!(canvas.materials[2][4], Some(Material::default()));
+        assert_eq!(canvas.materials[0][0], None);
+    }
+
+    #[test]
+    fn textures() {
+        let (red, green) = (Rgb::new(1.0, 0.0, 0.0), Rgb::new(0.0, 1.0, 0.0));
+        let mut wall = plane(3.0, 0.5, 0.5, -1.0, 0.0, 0.0);
+        wall.material.texture = Some(Texture::Checkerboard { a: red, b: green, size: 1.0 });
+        let ambient = Light::ambient(Rgb::WHITE, 1.0);
+        let mut canvas = Canvas::new(9, 9);
+        canvas.update(&camera(), &[&wall], &[&ambient]);
+        // Pixels are 2/3 apart on the wall, the centre one hits the middle of a square.
+        assert_eq!(canvas.colors[4][4], Some(red));
+        assert_eq!(canvas.colors[4][3], Some(green));
+        assert_eq!(canvas.colors[4][5], Some(green));
+        assert_eq!(canvas.colors[3][4], Some(green));
+    }
+
+    #[test]
+    fn post_processes() {
+        let wall = plane(3.0, 0.0, 0.0, -1.0, 0.0, 0.0);
+        let mut canvas = Canvas::new(9, 9);
+        canvas.post_processes = vec![PostProcess::Scanlines { strength: 1.0 }, PostProcess::Gamma { gamma: 0.5 }];
+        canvas.update(&camera(), &[&wall], &[]);
+        assert_eq!(canvas.colors[4][4], Some(Rgb::grey(0.8 * 0.8)));
+        assert_eq!(canvas.colors[3][4], Some(Rgb::BLACK));
+    }
+
+    #[test]
+    fn shading() {
+        let mut shiny = sphere(3.0, 0.0, 0.0, 1.0);
+        shiny.material = Material { specular: 1.0, shininess: 1.0, ..Material::from_color(Rgb::grey(0.5)) };
+        let floor = plane(0.0, 0.0, -1.0, 0.0, 0.0, -1.0);
+        let light = Light::directional(&Vector::from_xyz(1.0, 0.0, 0.0), Rgb::WHITE, 1.0).unwrap();
+        let ambient = Light::ambient(Rgb::WHITE, 0.25);
+        let mut canvas = Canvas::new(9, 9);
+        canvas.update(&camera(), &[&shiny, &floor], &[&light, &ambient]);
+
+        // Light and camera look at the centre of sphere: full diffuse and specular light.
+        assert!(canvas.normals[4][4].as_ref().unwrap().approximately_equal(&Vector::from_xyz(-1.0, 0.0, 0.0), 1e-5));
+        let center = canvas.colors[4][4].unwrap();
+        assert!((center.r - (0.125 + 0.5 + 1.0)).abs() < 1e-4);
+        // Closer to the edge of sphere light comes at an angle.
+        assert!(canvas.colors[4][3].unwrap().r < center.r);
+        // Floor faces away from camera, its normal is flipped; light grazes it, only ambient is left.
+        assert_eq!(canvas.normals[8][4], Some(Vector::from_xyz(0.0, 0.0, 1.0)));
+        assert_eq!(canvas.colors[8][4], Some(Rgb::grey(0.8 * 0.25)));
+        assert_eq!(canvas.colors[0][0], None);
+    }
+
+    #[test]
+    fn no_lights() {
+        let mut canvas = Canvas::new(3, 3);
+        canvas.update(&camera(), &[&sphere(3.0, 0.0, 0.0, 1.0)], &[]);
+        assert_eq!(canvas.colors[1][1], Some(Material::default().color));
+    }
+
+    #[test]
+    fn far_clip_and_fog() {
+        let mut canvas = Canvas::new(3, 3);
+        let mut camera = camera();
+        let target = sphere(3.0, 0.0, 0.0, 1.0);
+        camera.draw_distance = 1.5;
+        canvas.update(&camera, &[&target], &[]);
+        assert_eq!((canvas.distances[1][1], canvas.colors[1][1]), (None, None));
+        // Not positive draw distance does not clip.
+        camera.draw_distance = 0.0;
+        canvas.update(&camera, &[&target], &[]);
+        assert_eq!(canvas.colors[1][1], Some(Material::default().color));
+        // Half of colour is seen through linear fog at distance 2.
+        canvas.fog = Fog::new(FogMode::Linear, Rgb::WHITE, 0.25);
+        canvas.update(&camera, &[&target], &[]);
+        assert_eq!(canvas.colors[1][1], Some(Material::default().color.lerp(&Rgb::WHITE, 0.5)));
+    }
+
+    #[test]
+    fn supersampling() {
+        let target = sphere(3.0, 0.0, 0.0, 1.5);
+        let full = Material::default().color.luminance();
+        // Returns luminances of pixels, 0 for misses.
+        let render = |samples: usize, jittered: bool, adaptive: bool| {
+            let mut canvas = Canvas::new(9, 9);
+            (canvas.supersampling, canvas.jittered_sampling, canvas.adaptive_supersampling) = (samples, jittered, adaptive);
+            canvas.update(&camera(), &[&target], &[]);
+            (0..81).map(|k| canvas.colors[k / 9][k % 9].map_or(0.0, |color| color.luminance())).collect::<Vec<_>>()
+        };
+        let partial = |luminances: &[f32]| luminances.iter().filter(|l| 0.0 < **l && **l < full - 1e-3).count();
+
+        let single = render(1, false, false);
+        assert_eq!(partial(&single), 0);
+        for (jittered, adaptive) in [(false, false), (true, false), (false, true)] {
+            let supersampled = render(4, jittered, adaptive);
+            assert!(partial(&supersampled) > 4);
+            // The centre of sphere is covered by every ray.
+            assert!((supersampled[40] - full).abs() < 1e-3);
+            // Jitter is seeded by pixel, so frames do not flicker.
+            assert_eq!(render(4, jittered, adaptive), supersampled);
+        }
+    }
+
+    /// Returns colours of floor points `z = -1` seen by camera and their distances from `(x, y)` axis.
+    fn floor_colors(canvas: &Canvas, camera: &Camera, x: f32, y: f32) -> Vec<(f32, Rgb)> {
+        let rays = camera.get_hyperslice_rays_matrix(canvas.height, canvas.width);
+        let mut floor = vec![];
+        for i in 0..canvas.height {
+            for j in 0..canvas.width {
+                let (distance, color) = match (canvas.distances[i][j], canvas.colors[i][j]) {
+                    (Some(distance), Some(color)) => (distance, color),
+                    _ => continue,
+                };
+                let point = rays[i][j].point_at(distance);
+                if (point[2] + 1.0).abs() < 1e-4 {
+                    floor.push((((point[0] - x).powi(2) + (point[1] - y).powi(2)).sqrt(), color));
+                }
+            }
+        }
+        floor
+    }
+
+    #[test]
+    fn hard_shadows() {
+        let scene = [sphere(3.0, 0.0, -0.3, 0.3), plane(0.0, 0.0, -1.0, 0.0, 0.0, 1.0)];
+        let sun = Light::directional(&Vector::from_xyz(0.0, 0.0, -1.0), Rgb::WHITE, 1.0).unwrap();
+        let ambient = Light::ambient(Rgb::WHITE, 0.25);
+        let mut canvas = Canvas::new(40, 40);
+        canvas.update(&camera(), &scene.iter().collect::<Vec<_>>(), &[&sun, &ambient]);
+
+        let floor = floor_colors(&canvas, &camera(), 3.0, 0.0);
+        assert!(floor.iter().any(|(r, _)| *r < 0.25));
+        for (r, color) in floor {
+            if r < 0.29 {
+                assert_eq!(color, Rgb::grey(0.8 * 0.25));
+            } else if r > 0.31 {
+                assert_eq!(color, Rgb::grey(0.8 * 1.25));
+            }
+        }
+        // Sphere does not shadow itself.
+        assert!(canvas.colors[20][20].unwrap().r > 0.8 * 0.25);
+    }
+
+    #[test]
+    fn soft_shadows() {
+        let scene = [sphere(3.0, 0.0, -0.3, 0.3), plane(0.0, 0.0, -1.0, 0.0, 0.0, 1.0)];
+        let sun = Light { radius: 0.2, ..Light::directional(&Vector::from_xyz(0.0, 0.0, -1.0), Rgb::WHITE, 1.0).unwrap() };
+        let mut canvas = Canvas::new(40, 40);
+        canvas.shadow_samples = 16;
+        canvas.update(&camera(), &scene.iter().collect::<Vec<_>>(), &[&sun]);
+
+        let floor = floor_colors(&canvas, &camera(), 3.0, 0.0);
+        let penumbra = floor.iter().filter(|(_, color)| 0.0 < color.r && color.r < 0.8).count();
+        assert!(penumbra > 0);
+        // Far from the sphere floor is fully lit.
+        assert!(floor.iter().filter(|(r, _)| *r > 1.0).all(|(_, color)| *color == Rgb::grey(0.8)));
+    }
+
+    #[test]
+    fn culled_objects_cast_shadows() {
+        let target = sphere(3.0, 0.0, 0.0, 1.0);
+        let behind = sphere(-2.0, 0.0, 0.0, 1.0);
+        let lamp = Light::point(Vector::from_xyz(-4.0, 0.0, 0.0), Rgb::WHITE, 16.0);
+        let mut canvas = Canvas::new(9, 9);
+        canvas.update(&camera(), &[&target], &[&lamp]);
+        assert!(canvas.colors[4][4].unwrap().r > 0.0);
+        canvas.update(&camera(), &[&target, &behind], &[&lamp]);
+        assert_eq!(canvas.culled_count, 1);
+        assert_eq!(canvas.colors[4][4], Some(Rgb::BLACK));
+    }
+
+    #[test]
+    fn mirror() {
+        let mut mirror = plane(5.0, 0.0, 0.0, -1.0, 0.0, 0.0);
+        mirror.material = Material { reflectivity: 1.0, ..Material::default() };
+        // Sphere behind camera is culled but seen in the mirror.
+        let mut red = sphere(-3.0, 0.0, 0.0, 1.0);
+        red.material = Material::from_color(Rgb::new(1.0, 0.0, 0.0));
+        let mut canvas = Canvas::new(9, 9);
+        canvas.update(&camera(), &[&mirror, &red], &[]);
+        assert_eq!(canvas.culled_count, 1);
+        assert_eq!(canvas.colors[4][4], Some(Rgb::new(1.0, 0.0, 0.0)));
+
+        canvas.max_bounces = 0;
+        canvas.update(&camera(), &[&mirror, &red], &[]);
+        assert_eq!(canvas.colors[4][4], Some(Material::default().color));
+    }
+
+    #[test]
+    fn glass() {
+        let mut glass = sphere(3.0, 0.0, 0.0, 1.0);
+        glass.material = Material { transparency: 1.0, refractive_index: 1.0, ..Material::default() };
+        let mut green = sphere(8.0, 0.0, 0.0, 1.0);
+        green.material = Material::from_color(Rgb::new(0.0, 1.0, 0.0));
+        let mut canvas = Canvas::new(9, 9);
+        // Glass with refractive index of air does not bend rays which enter and leave it.
+        canvas.max_bounces = 2;
+        canvas.update(&camera(), &[&glass, &green], &[]);
+        let color = canvas.colors[4][4].unwrap();
+        assert!(color.g > 0.99 && color.r < 1e-3);
+
+        // Lens of real glass bends rays.
+        glass.material.refractive_index = 1.5;
+        canvas.update(&camera(), &[&glass, &green], &[]);
+        assert_eq!(canvas.distances[4][4], Some(2.0));
+        assert!(canvas.colors[4][4].unwrap().g > 0.5);
+    }
+
+    #[test]
+    fn fresnel() {
+        use crate::utils::assert_eq_f32;
+
+        assert_eq_f32(schlick(1.0, 1.0, 1.5), 0.04);
+        assert_eq_f32(schlick(0.0, 1.0, 1.5), 1.0);
+        assert_eq_f32(schlick(0.5, 1.0, 1.0), 0.5_f32.powi(5));
+
+        let normal = Vector::from_xyz(0.0, 0.0, 1.0);
+        let mut direction = Vector::from_xyz(1.0, 0.0, -1.0);
+        direction.normalize();
+        assert!(reflect(&direction, &normal).approximately_equal(&Vector::from_xyz(direction[0], 0.0, -direction[2]), 1e-6));
+        // Snell's law: sin of refracted angle is `eta` times sin of incident angle.
+        let refracted = refract(&direction, &normal, 1.0 / 1.5).unwrap();
+        assert_eq_f32(refracted.magnitude(), 1.0);
+        assert_eq_f32(refracted[0], direction[0] / 1.5);
+        // Total internal reflection when leaving glass at a grazing angle.
+        assert!(refract(&direction, &normal, 1.5).is_none());
+    }
+
+    #[test]
+    fn edges() {
+        let scene = [sphere(3.0, 0.0, 0.0, 1.0), plane(0.0, 0.0, -1.0, 0.0, 0.0, 1.0), plane(20.0, 0.0, 0.0, -1.0, 0.0, 0.0)];
+        let mut canvas = Canvas::new(21, 21);
+        canvas.update(&camera(), &scene.iter().collect::<Vec<_>>(), &[]);
+        assert!(!canvas.is_edge(10, 10));
+        // Flat floor and back wall, also seen at a grazing angle.
+        assert!(!canvas.is_edge(20, 1));
+        assert!(!canvas.is_edge(15, 1));
+        assert!(!canvas.is_edge(1, 1));
+        // Silhouette of sphere is one pixel wide.
+        assert_eq!((0..21).filter(|j| canvas.is_edge(9, *j)).collect::<Vec<_>>(), vec![7, 13]);
+        // Corner between floor and back wall.
+        assert!((0..21).any(|i| canvas.is_edge(i, 0)));
+    }
+
+    #[test]
+    fn size_of_render_mode() {
+        let config = GameConfig { screen_width: 30, screen_height: 10, render_mode: RenderMode::Braille, ..GameConfig::default() };
+        let canvas = Canvas::new_from_game_config(&config);
+        assert_eq!((canvas.width, canvas.height), (60, 40));
+        assert_eq!((canvas.colors.cols_count(), canvas.colors.rows_count()), (60, 40));
+    }
+}
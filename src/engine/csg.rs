@@ -0,0 +1,262 @@
+use super::*;
+use crate::math::*;
+
+
+/// Returns intervals of `object` which transform is relative to the position of combination.
+fn local_intervals(object: &SceneObject, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+    match transform.to_local_ray(ray) {
+        None => vec![],
+        Some(local_ray) => object.game_object.intersection_intervals(&object.transform, &local_ray),
+    }
+}
+
+/// Returns bounding box of `object` relative to the position of combination.
+fn local_bounding_box(object: &SceneObject) -> Option<Aabb> {
+    object.game_object.bounding_box(&object.transform)
+}
+
+/// Returns normal of `object` at `point` and how far `point` is from the surface of `object`
+/// along the normal. Normal and point are given relative to the position of combination.
+fn local_normal(object: &SceneObject, transform: &Transform, point: &Vector<f32>) -> Option<(Vector<f32>, f32)> {
+    let point = transform.to_local_point(point)?;
+    let normal = object.game_object.normal(&object.transform, &point)?;
+    let probe = Ray::new(point, normal.clone());
+    let error = object.game_object
+        .intersection_intervals(&object.transform, &probe)
+        .iter()
+        .flat_map(|interval| [interval.enter, interval.exit])
+        .filter(|t| t.is_finite())
+        .map(f32::abs)
+        .min_by(|a, b| a.total_cmp(b))?;
+    Some((normal, error))
+}
+
+/// Returns normal of the object which surface is the nearest to `point`.
+/// Normal of `b` is negated if `b` is subtracted.
+fn combined_normal(
+    a: &SceneObject, b: &SceneObject, subtract_b: bool, transform: &Transform, point: &Vector<f32>
+) -> Option<Vector<f32>> {
+    let a = local_normal(a, transform, point);
+    let b = local_normal(b, transform, point).map(|(normal, error)| {
+        if subtract_b { (-normal, error) } else { (normal, error) }
+    });
+    match (a, b) {
+        (Some((a, a_error)), Some((b, b_error))) => Some(if a_error <= b_error { a } else { b }),
+        (a, b) => a.or(b).map(|(normal, _)| normal),
+    }
+}
+
+/// Constructive solid geometry: points which are inside of `a` or inside of `b`.
+/// Transforms of `a` and `b` are relative to the transform of union.
+pub struct Union {
+    pub a: SceneObject,
+    pub b: SceneObject,
+}
+
+impl Union {
+    pub fn new(a: SceneObject, b: SceneObject) -> Union {
+        Union { a, b }
+    }
+}
+
+impl GameObject for Union {
+    fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        nearest_interval_distance(&self.intersection_intervals(transform, ray))
+    }
+
+    fn intersection_intervals(&self, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+        Interval::union(
+            &local_intervals(&self.a, transform, ray),
+            &local_intervals(&self.b, transform, ray),
+        )
+    }
+
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        combined_normal(&self.a, &self.b, false, transform, point)
+    }
+
+    /// Union is unbounded if any of its objects is unbounded.
+    fn bounding_box(&self, transform: &Transform) -> Option<Aabb> {
+        let a = local_bounding_box(&self.a)?;
+        let b = local_bounding_box(&self.b)?;
+        transform.to_world_box(&a.union(&b))
+    }
+}
+
+/// Constructive solid geometry: points which are inside of both `a` and `b`.
+/// Transforms of `a` and `b` are relative to the transform of intersection.
+pub struct Intersection {
+    pub a: SceneObject,
+    pub b: SceneObject,
+}
+
+impl Intersection {
+    pub fn new(a: SceneObject, b: SceneObject) -> Intersection {
+        Intersection { a, b }
+    }
+}
+
+impl GameObject for Intersection {
+    fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        nearest_interval_distance(&self.intersection_intervals(transform, ray))
+    }
+
+    fn intersection_intervals(&self, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+        Interval::intersection(
+            &local_intervals(&self.a, transform, ray),
+            &local_intervals(&self.b, transform, ray),
+        )
+    }
+
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        combined_normal(&self.a, &self.b, false, transform, point)
+    }
+
+    /// Intersection is bounded if any of its objects is bounded.
+    fn bounding_box(&self, transform: &Transform) -> Option<Aabb> {
+        let local_box = match (local_bounding_box(&self.a), local_bounding_box(&self.b)) {
+            (None, None) => return None,
+            (Some(a), None) => a,
+            (None, Some(b)) => b,
+            // Disjoint boxes mean that intersection is empty, any of the boxes fits then.
+            (Some(a), Some(b)) => a.intersection(&b).unwrap_or(a),
+        };
+        transform.to_world_box(&local_box)
+    }
+}
+
+/// Constructive solid geometry: points which are inside of `a` but outside of `b`.
+/// Transforms of `a` and `b` are relative to the transform of difference.
+pub struct Difference {
+    pub a: SceneObject,
+    pub b: SceneObject,
+}
+
+impl Difference {
+    pub fn new(a: SceneObject, b: SceneObject) -> Difference {
+        Difference { a, b }
+    }
+}
+
+impl GameObject for Difference {
+    fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        nearest_interval_distance(&self.intersection_intervals(transform, ray))
+    }
+
+    fn intersection_intervals(&self, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+        Interval::difference(
+            &local_intervals(&self.a, transform, ray),
+            &local_intervals(&self.b, transform, ray),
+        )
+    }
+
+    /// Surface of `b` looks inside of `b`, because it bounds the hole.
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        combined_normal(&self.a, &self.b, true, transform, point)
+    }
+
+    fn bounding_box(&self, transform: &Transform) -> Option<Aabb> {
+        transform.to_world_box(&local_bounding_box(&self.a)?)
+    }
+}
+
+
+#[cfg(test)]
+mod csg_tests {
+    use crate::utils::assert_eq_option_f32;
+
+    use super::*;
+
+    fn sphere(x: f32, r: f32) -> SceneObject {
+        SceneObject::new(
+            Transform::new_from_coords(x, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap(),
+            Box::new(Hyperellipsoid::new3d(r, r, r)),
+        )
+    }
+
+    /// Slab `0 <= x <= 1` made of two half-spaces.
+    fn wall() -> SceneObject {
+        let back = SceneObject::new(
+            Transform::new_from_coords(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap(),
+            Box::new(Hyperplane),
+        );
+        let front = SceneObject::new(
+            Transform::new_from_coords(0.0, 0.0, 0.0, -1.0, 0.0, 0.0).unwrap(),
+            Box::new(Hyperplane),
+        );
+        SceneObject::new(Transform::default(), Box::new(Intersection::new(back, front)))
+    }
+
+    fn ray(y: f32) -> Ray {
+        Ray::new(Vector::from_xyz(-5.0, y, 0.0), Vector::from_xyz(1.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn union_of_spheres() {
+        let u = Union::new(sphere(0.0, 1.0), sphere(1.5, 1.0));
+        assert_eq!(u.intersection_intervals(&Transform::default(), &ray(0.0)), vec![Interval::new(4.0, 7.5)]);
+        assert_eq_option_f32(u.intersection_distance(&Transform::default(), &ray(0.0)), Some(4.0));
+    }
+
+    #[test]
+    fn lens() {
+        let lens = Intersection::new(sphere(-1.0, 2.0), sphere(1.0, 2.0));
+        assert_eq_option_f32(lens.intersection_distance(&Transform::default(), &ray(0.0)), Some(4.0));
+        assert_eq_option_f32(lens.intersection_distance(&Transform::default(), &ray(1.8)), None);
+    }
+
+    #[test]
+    fn lens_moved() {
+        let lens = Intersection::new(sphere(-1.0, 2.0), sphere(1.0, 2.0));
+        let t = Transform::new_from_coords(0.0, 10.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        assert_eq_option_f32(lens.intersection_distance(&t, &ray(10.0)), Some(4.0));
+        assert_eq_option_f32(lens.intersection_distance(&t, &ray(0.0)), None);
+    }
+
+    #[test]
+    fn door_in_wall() {
+        let door = Difference::new(wall(), sphere(0.0, 2.0));
+        assert_eq_option_f32(door.intersection_distance(&Transform::default(), &ray(0.0)), None);
+        assert_eq_option_f32(door.intersection_distance(&Transform::default(), &ray(5.0)), Some(5.0));
+    }
+
+    #[test]
+    fn carved_sphere_from_inside() {
+        // Ray starts inside of the sphere and leaves it through the carved hole.
+        let carved = Difference::new(sphere(0.0, 3.0), sphere(2.0, 1.0));
+        let r = Ray::new(Vector::zero3(), Vector::from_xyz(1.0, 0.0, 0.0));
+        assert_eq_option_f32(carved.intersection_distance(&Transform::default(), &r), Some(1.0));
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        let t = Transform::new_from_coords(10.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        let union = Union::new(sphere(0.0, 1.0), sphere(1.5, 1.0));
+        assert_eq!(
+            union.bounding_box(&t),
+            Aabb::new(Vector::from_xyz(9.0, -1.0, -1.0), Vector::from_xyz(12.5, 1.0, 1.0))
+        );
+        let lens = Intersection::new(sphere(-1.0, 2.0), sphere(1.0, 2.0));
+        assert_eq!(
+            lens.bounding_box(&t),
+            Aabb::new(Vector::from_xyz(9.0, -2.0, -2.0), Vector::from_xyz(11.0, 2.0, 2.0))
+        );
+        assert!(Union::new(sphere(0.0, 1.0), wall()).bounding_box(&t).is_none());
+        assert!(Difference::new(wall(), sphere(0.0, 1.0)).bounding_box(&t).is_none());
+        assert!(Difference::new(sphere(0.0, 1.0), wall()).bounding_box(&t).is_some());
+    }
+
+    #[test]
+    fn normals() {
+        let t = Transform::default();
+        let door = Difference::new(wall(), sphere(0.0, 2.0));
+        let n = door.normal(&t, &Vector::from_xyz(0.0, 5.0, 0.0)).unwrap();
+        assert!(n.approximately_equal(&Vector::from_xyz(-1.0, 0.0, 0.0), 1e-5));
+        // Inside of the hole normal looks to its center.
+        let n = door.normal(&t, &Vector::from_xyz(0.5, 0.0, 3.75_f32.sqrt())).unwrap();
+        assert!(n.approximately_equal(&Vector::from_xyz(-0.25, 0.0, -3.75_f32.sqrt() / 2.0), 1e-5));
+        let union = Union::new(sphere(0.0, 1.0), sphere(1.5, 1.0));
+        let n = union.normal(&t, &Vector::from_xyz(2.5, 0.0, 0.0)).unwrap();
+        assert!(n.approximately_equal(&Vector::from_xyz(1.0, 0.0, 0.0), 1e-5));
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::*;
+
+
+/// How visibility of surfaces falls with distance in fog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FogMode {
+    /// Air is clear.
+    #[default]
+    None,
+    /// Visibility falls linearly and surfaces vanish at distance `1 / density`.
+    Linear,
+    /// Visibility is `exp(-density * distance)`, like in uniform medium.
+    Exponential,
+    /// Visibility is `exp(-(density * distance)²)`, fog is thin near the camera and thickens fast.
+    ExponentialSquared,
+}
+
+impl fmt::Display for FogMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FogMode::None => "none",
+            FogMode::Linear => "linear",
+            FogMode::Exponential => "exponential",
+            FogMode::ExponentialSquared => "exponential_squared",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for FogMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FogMode, String> {
+        match s {
+            "none" => Ok(FogMode::None),
+            "linear" => Ok(FogMode::Linear),
+            "exponential" => Ok(FogMode::Exponential),
+            "exponential_squared" => Ok(FogMode::ExponentialSquared),
+            _ => Err(format!("Unknown fog mode `{}`", s)),
+        }
+    }
+}
+
+/// Fog which blends colours of distant surfaces with `color`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Rgb,
+    pub density: f32,
+}
+
+impl Fog {
+    pub fn new(mode: FogMode, color: Rgb, density: f32) -> Fog {
+        Fog { mode, color, density }
+    }
+
+    pub fn new_from_game_config(config: &GameConfig) -> Fog {
+        Fog::new(config.fog_mode, config.fog_color, config.fog_density)
+    }
+
+    /// Returns part of surface colour which is seen through fog at `distance`, from 0 to 1.
+    pub fn visibility(&self, distance: f32) -> f32 {
+        let depth = self.density.max(0.0) * distance;
+        match self.mode {
+            FogMode::None => 1.0,
+            FogMode::Linear => (1.0 - depth).clamp(0.0, 1.0),
+            FogMode::Exponential => (-depth).exp(),
+            FogMode::ExponentialSquared => (-depth * depth).exp(),
+        }
+    }
+
+    /// Returns `color` of surface at `distance` seen through fog.
+    pub fn apply(&self, color: Rgb, distance: f32) -> Rgb {
+        self.color.lerp(&color, self.visibility(distance))
+    }
+}
+
+
+#[cfg(test)]
+mod fog_tests {
+    use super::*;
+
+    #[test]
+    fn visibility() {
+        let fog = |mode| Fog::new(mode, Rgb::WHITE, 0.5);
+        assert_eq!(fog(FogMode::None).visibility(100.0), 1.0);
+        assert_eq!(fog(FogMode::Linear).visibility(1.0), 0.5);
+        assert_eq!(fog(FogMode::Linear).visibility(3.0), 0.0);
+        assert!((fog(FogMode::Exponential).visibility(2.0) - (-1.0_f32).exp()).abs() < 1e-6);
+        assert!((fog(FogMode::ExponentialSquared).visibility(4.0) - (-4.0_f32).exp()).abs() < 1e-6);
+        assert_eq!(fog(FogMode::Exponential).visibility(f32::INFINITY), 0.0);
+        assert_eq!(fog(FogMode::Linear).apply(Rgb::BLACK, 1.0), Rgb::grey(0.5));
+    }
+
+    #[test]
+    fn parse() {
+        for mode in [FogMode::None, FogMode::Linear, FogMode::Exponential, FogMode::ExponentialSquared] {
+            assert_eq!(mode.to_string().parse::<FogMode>(), Ok(mode));
+        }
+        assert!("exponential2".parse::<FogMode>().is_err());
+    }
+}
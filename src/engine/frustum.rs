@@ -0,0 +1,221 @@
+use super::*;
+use crate::math::*;
+
+
+/// Half-space of points `x` where `normal · x + offset >= 0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plane {
+    pub normal: Vector<f32>,
+    pub offset: f32,
+}
+
+impl Plane {
+    /// Creates plane through `point` with `normal` pointing inside of half-space.
+    pub fn from_point(normal: Vector<f32>, point: &Vector<f32>) -> Plane {
+        let offset = -normal.dot_product(point);
+        Plane { normal, offset }
+    }
+
+    /// Returns signed distance scaled by the normal length, positive inside of half-space.
+    pub fn signed_distance(&self, point: &Vector<f32>) -> f32 {
+        self.normal.dot_product(point) + self.offset
+    }
+
+    /// Returns true if the whole box lies outside of half-space.
+    pub fn is_outside(&self, aabb: &Aabb) -> bool {
+        // Vertex of box which is the farthest inside along the normal.
+        let vertex = Vector::new(
+            (0..aabb.dim())
+                .map(|i| if self.normal[i] >= 0.0 { aabb.max[i] } else { aabb.min[i] })
+                .collect(),
+        );
+        self.signed_distance(&vertex) < 0.0
+    }
+}
+
+/// Region of space visible by camera: intersection of half-spaces.
+///
+/// In 3d it is a truncated pyramid. In 4d and higher it is additionally
+/// squeezed to the rendered 3d hyperslice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frustum {
+    pub planes: Vec<Plane>,
+}
+
+impl Frustum {
+    /// Builds frustum of rays made by `Camera::get_hyperslice_rays_matrix`.
+    ///
+    /// Side planes are skipped for field of view of 180 degrees or wider.
+    /// Far plane is skipped if camera `draw_distance` is not positive.
+    /// Fisheye frustum is a pyramid around the cone of its rays, it has only hyperslice planes
+    /// if the cone is wider than 180 degrees. Equirectangular camera sees everything in the hyperslice.
+    pub fn from_camera(camera: &Camera) -> Frustum {
+        let origin = camera.get_hyperslice_origin();
+        let (forward, left, up) = (&camera.frame.base[0], &camera.frame.base[1], &camera.frame.base[2]);
+        let mut planes = Vec::new();
+        match camera.projection {
+            Projection::Perspective => {
+                planes.extend(Frustum::depth_planes(camera, &origin));
+                planes.extend(Frustum::side_planes(camera.horizontal_fov, forward, left, &origin));
+                planes.extend(Frustum::side_planes(camera.vertical_fov, forward, up, &origin));
+            }
+            Projection::Orthographic { width } => {
+                planes.extend(Frustum::depth_planes(camera, &origin));
+                let (half_width, half_height) = (0.5 * width, 0.5 * width / camera.aspect_ratio);
+                for (side, half_size) in [(left, half_width), (up, half_height)] {
+                    planes.push(Plane::from_point(-side.clone(), &(&origin + &(side * half_size)).unwrap()));
+                    planes.push(Plane::from_point(side.clone(), &(&origin - &(side * half_size)).unwrap()));
+                }
+            }
+            Projection::Fisheye => {
+                // Angle between view direction and rays at corners of the screen.
+                let corner = 0.5 * camera.horizontal_fov * (1.0 / camera.aspect_ratio).hypot(1.0);
+                if corner < std::f32::consts::FRAC_PI_2 {
+                    planes.extend(Frustum::depth_planes(camera, &origin));
+                    planes.extend(Frustum::side_planes(2.0 * corner, forward, left, &origin));
+                    planes.extend(Frustum::side_planes(2.0 * corner, forward, up, &origin));
+                }
+            }
+            Projection::Equirectangular => {}
+        }
+
+        // Rays do not leave the hyperslice.
+        for normal in camera.frame.base.iter().skip(3) {
+            planes.push(Plane::from_point(normal.clone(), &origin));
+            planes.push(Plane::from_point(-normal.clone(), &origin));
+        }
+        Frustum { planes }
+    }
+
+    /// Returns near plane through `origin` and far plane at camera `draw_distance`.
+    fn depth_planes(camera: &Camera, origin: &Vector<f32>) -> Vec<Plane> {
+        let forward = &camera.frame.base[0];
+        let mut planes = vec![Plane::from_point(forward.clone(), origin)];
+        if camera.draw_distance > 0.0 {
+            let far_point = (origin + &(forward * camera.draw_distance)).unwrap();
+            planes.push(Plane::from_point(-forward.clone(), &far_point));
+        }
+        planes
+    }
+
+    /// Returns planes through `origin` which bound rays within `fov` along `side`.
+    fn side_planes(fov: f32, forward: &Vector<f32>, side: &Vector<f32>, origin: &Vector<f32>) -> Vec<Plane> {
+        if fov >= std::f32::consts::PI {
+            return Vec::new();
+        }
+        // Boundary rays are `forward ± tan(fov / 2) * side`.
+        let tangent = (0.5 * fov).tan();
+        let along = forward * tangent;
+        vec![
+            Plane::from_point((&along - side).unwrap(), origin),
+            Plane::from_point((&along + side).unwrap(), origin),
+        ]
+    }
+
+    /// Returns true if `point` is inside of frustum or on its border.
+    pub fn contains_point(&self, point: &Vector<f32>) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    /// Returns false if box is certainly invisible, i.e. it lies outside of some plane.
+    /// Boxes near corners of frustum may be kept though they are invisible.
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.normal.dim() != aabb.dim() || !plane.is_outside(aabb))
+    }
+}
+
+
+#[cfg(test)]
+mod frustum_tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    fn camera(frame: CoordinateSystem) -> Camera {
+        let config = GameConfig {
+            camera_fov: PI / 2.0,
+            camera_draw_distance: 10.0,
+            ..GameConfig::default()
+        };
+        let mut camera = Camera::new_in_frame(frame, &config).unwrap();
+        camera.vertical_fov = PI / 2.0;
+        camera
+    }
+
+    fn cube(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb::around(&Vector::from_xyz(x, y, z), 0.5)
+    }
+
+    #[test]
+    fn contains_point() {
+        let frustum = Frustum::from_camera(&camera(CoordinateSystem::default3()));
+        assert_eq!(frustum.planes.len(), 6);
+        assert!(frustum.contains_point(&Vector::from_xyz(5.0, 4.0, -4.0)));
+        assert!(!frustum.contains_point(&Vector::from_xyz(5.0, 6.0, 0.0)));
+        assert!(!frustum.contains_point(&Vector::from_xyz(-1.0, 0.0, 0.0)));
+        assert!(!frustum.contains_point(&Vector::from_xyz(11.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn intersects() {
+        let frustum = Frustum::from_camera(&camera(CoordinateSystem::default3()));
+        assert!(frustum.intersects(&cube(3.0, 0.0, 0.0)));
+        // Box around camera.
+        assert!(frustum.intersects(&cube(0.0, 0.0, 0.0)));
+        // Box partially inside of the left side.
+        assert!(frustum.intersects(&cube(3.0, 3.4, 0.0)));
+        assert!(!frustum.intersects(&cube(3.0, 4.1, 0.0)));
+        assert!(!frustum.intersects(&cube(-3.0, 0.0, 0.0)));
+        assert!(!frustum.intersects(&cube(3.0, 0.0, -4.1)));
+        assert!(!frustum.intersects(&cube(11.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn rotated_camera() {
+        let mut camera = camera(CoordinateSystem::default3());
+        camera.rotate(0, 1, PI / 2.0).unwrap();
+        let frustum = Frustum::from_camera(&camera);
+        assert!(frustum.intersects(&cube(0.0, 3.0, 0.0)));
+        assert!(!frustum.intersects(&cube(3.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn unlimited_draw_distance() {
+        let mut camera = camera(CoordinateSystem::default3());
+        camera.draw_distance = 0.0;
+        assert!(Frustum::from_camera(&camera).intersects(&cube(1000.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn hyperslice() {
+        let mut camera = camera(CoordinateSystem::identity(4));
+        camera.hyperslice_offset = 1.0;
+        let frustum = Frustum::from_camera(&camera);
+        let tesseract = |w: f32| Aabb::around(&Vector::new(vec![3.0, 0.0, 0.0, w]), 0.5);
+        assert!(frustum.intersects(&tesseract(1.2)));
+        assert!(!frustum.intersects(&tesseract(0.0)));
+    }
+
+    #[test]
+    fn projections() {
+        let mut camera = camera(CoordinateSystem::default3());
+        camera.aspect_ratio = 2.0;
+        camera.projection = Projection::Orthographic { width: 4.0 };
+        let frustum = Frustum::from_camera(&camera);
+        assert!(frustum.intersects(&cube(1.0, 2.3, 0.0)));
+        assert!(!frustum.intersects(&cube(1.0, 2.6, 0.0)));
+        assert!(!frustum.intersects(&cube(5.0, 0.0, 1.6)));
+
+        // Fisheye of 180 degrees sees behind its sides.
+        camera.horizontal_fov = PI;
+        camera.projection = Projection::Fisheye;
+        assert!(Frustum::from_camera(&camera).intersects(&cube(-1.0, 5.0, 0.0)));
+        camera.horizontal_fov = PI / 2.0;
+        assert!(!Frustum::from_camera(&camera).intersects(&cube(-1.0, 5.0, 0.0)));
+
+        camera.projection = Projection::Equirectangular;
+        assert!(Frustum::from_camera(&camera).intersects(&cube(-3.0, 0.0, 0.0)));
+    }
+}
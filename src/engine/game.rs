@@ -1,7 +1,6 @@
 use super::*;
 use crate::math::*;
-use std::borrow::Borrow;
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
 use std::default;
 use std::rc::Rc;
 use std::time;
@@ -10,10 +9,42 @@ use std::time::Instant;
 use console_engine::ConsoleEngine;
 use console_engine::Color;
 use console_engine::KeyCode;
+use console_engine::pixel;
 use console_engine::pixel::Pixel;
 
 
-pub type GameObjects = Vec<Rc<RefCell<Box<dyn GameObject>>>>;
+pub type GameObjects = Vec<Rc<RefCell<SceneObject>>>;
+pub type Lights = Vec<Rc<RefCell<Light>>>;
+
+/// Camera rotation speed in radians per second.
+const CAMERA_ROTATION_SPEED: f32 = 1.0;
+/// Speed of moving hyperslice in units per second.
+const HYPERSLICE_SPEED: f32 = 1.0;
+/// Keys which rotate camera: (key for positive angle, key for negative angle, a, b).
+/// Camera rotates in the plane spanned by `frame.base[a]` and `frame.base[b]`.
+/// Planes with axes out of camera dimension are skipped.
+const CAMERA_ROTATION_KEYS: [(char, char, usize, usize); 6] = [
+    ('a', 'd', 0, 1),
+    ('r', 'f', 0, 2),
+    ('z', 'c', 1, 2),
+    ('u', 'j', 0, 3),
+    ('i', 'k', 1, 3),
+    ('o', 'l', 2, 3),
+];
+/// Keys which move hyperslice along `frame.base[3]`: (forward, backward).
+const HYPERSLICE_KEYS: (char, char) = (']', '[');
+/// Edge length of cells of `Game::spatial_hash`.
+const SPATIAL_HASH_CELL_SIZE: f32 = 4.0;
+/// Walking speed in tile map mode in tiles per second.
+const WALK_SPEED: f32 = 2.0;
+/// Keys which move camera in tile map mode: (forward, backward).
+const WALK_KEYS: (char, char) = ('w', 's');
+/// Key which opens or closes the door in front of camera in tile map mode.
+const DOOR_KEY: char = 'e';
+/// Pixels brighter than this are drawn as raised dots in Braille render mode.
+const BRAILLE_THRESHOLD: f32 = 0.2;
+/// Key which passes controls to the next viewport.
+const NEXT_VIEWPORT_KEY: KeyCode = KeyCode::Tab;
 
 
 
@@ -48,11 +79,21 @@ Es: EventSystemT<E> + 'static, {
     pub entities: Vec<Entity>,
     pub config: GameConfig,
     pub console_engine: ConsoleEngine,
-    pub camera: Camera,
-    pub canvas: Canvas,
+    /// Cameras with their rectangles of the screen, drawn in order. There is at least one viewport.
+    viewports: Vec<Viewport>,
+    /// Arrangement of `viewports` on the screen.
+    layout: Layout,
+    /// Index of viewport which camera is controlled by keyboard.
+    pub active_viewport: usize,
     pub event_system: Es,
     pub clock: Clock,
     pub game_objects: GameObjects,
+    /// Light sources of 3d mode. Without lights the world is lit by white ambient light.
+    pub lights: Lights,
+    /// Bounding boxes of `game_objects` by their indices, updated every frame before rendering.
+    pub spatial_hash: SpatialHash,
+    /// If set, world is rendered in 2.5d tile map mode instead of 3d canvases of viewports.
+    pub tile_map: Option<TileMap>,
     _phantom_data: Option<&'a E>,
 }
 
@@ -60,6 +101,8 @@ impl<'a, E, Es> Game<'a, E, Es> where
 E: EventT + 'static,
 Es: EventSystemT<E> + 'static {
     pub fn new(coordinate_system: CoordinateSystem, config: GameConfig) -> Game<'a, E, Es> {
+        let camera = Camera::new_in_frame(coordinate_system.clone(), &config)
+            .expect("Coordinate system must have at least 3 dimensions.");
         Game { 
             coordinate_system, 
             entities: vec![],
@@ -68,12 +111,16 @@ Es: EventSystemT<E> + 'static {
                 config.screen_height as u32,
                 config.target_fps as u32
             ).expect("Internal problem with creating console engine."),
-            camera: Camera::new(Transform::default(), &config),
-            canvas: Canvas::new_from_game_config(&config),
+            viewports: vec![Viewport::new(camera, Rect::screen(&config), &config)],
+            layout: Layout::default(),
+            active_viewport: 0,
+            tile_map: None,
             config,
             event_system: Es::default(),
             clock: Clock::default(),
             game_objects: vec![],
+            lights: vec![],
+            spatial_hash: SpatialHash::new(SPATIAL_HASH_CELL_SIZE).unwrap(),
             _phantom_data: None,
         }
     }
@@ -100,12 +147,331 @@ Es: EventSystemT<E> + 'static {
         }
     }
 
+    /// Places game object in the world and returns shared reference to it.
+    pub fn add_game_object(&mut self, transform: Transform, game_object: Box<dyn GameObject>) -> Rc<RefCell<SceneObject>> {
+        let scene_object = Rc::new(RefCell::new(SceneObject::new(transform, game_object)));
+        self.game_objects.push(scene_object.clone());
+        scene_object
+    }
+
+    /// Adds light source to the world and returns shared reference to it.
+    pub fn add_light(&mut self, light: Light) -> Rc<RefCell<Light>> {
+        let light = Rc::new(RefCell::new(light));
+        self.lights.push(light.clone());
+        light
+    }
+
+    /// Returns camera controlled by keyboard.
+    pub fn camera(&self) -> &Camera {
+        &self.viewports[self.active_viewport].camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.viewports[self.active_viewport].camera
+    }
+
+    pub fn viewports(&self) -> &[Viewport] {
+        &self.viewports
+    }
+
+    pub fn viewports_mut(&mut self) -> &mut [Viewport] {
+        &mut self.viewports
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Adds viewport of `camera` and returns its index. Viewport takes `rect` in `Layout::Manual`,
+    /// other layouts split the screen among all viewports again.
+    pub fn add_viewport(&mut self, camera: Camera, rect: Rect) -> usize {
+        self.viewports.push(Viewport::new(camera, rect, &self.config));
+        self.apply_layout();
+        self.viewports.len() - 1
+    }
+
+    /// Removes viewport at `index` and returns it.
+    /// The last viewport can not be removed, then `None` is returned.
+    pub fn remove_viewport(&mut self, index: usize) -> Option<Viewport> {
+        if index >= self.viewports.len() || self.viewports.len() == 1 {
+            return None;
+        }
+        let viewport = self.viewports.remove(index);
+        if self.active_viewport >= self.viewports.len() || self.active_viewport > index {
+            self.active_viewport -= 1;
+        }
+        self.apply_layout();
+        Some(viewport)
+    }
+
+    /// Arranges viewports by `layout`.
+    pub fn set_layout(&mut self, layout: Layout) {
+        self.layout = layout;
+        self.apply_layout();
+    }
+
+    /// Moves viewport at `index` to `rect` and switches layout to `Layout::Manual`,
+    /// so rectangles of other viewports are kept.
+    pub fn set_viewport_rect(&mut self, index: usize, rect: Rect) -> Result<(), ()> {
+        let viewport = self.viewports.get_mut(index).ok_or(())?;
+        viewport.set_rect(rect, &self.config);
+        self.layout = Layout::Manual;
+        Ok(())
+    }
+
+    /// Moves viewports to rectangles of `layout`.
+    fn apply_layout(&mut self) {
+        let rects = self.layout.rects(self.config.screen_width, self.config.screen_height, self.viewports.len());
+        for (viewport, rect) in self.viewports.iter_mut().zip(rects.into_iter().flatten()) {
+            viewport.set_rect(rect, &self.config);
+        }
+    }
+
+    /// Switches game to tile map mode and places cameras at the map spawn point
+    /// with eye in the middle of wall height.
+    pub fn set_tile_map(&mut self, tile_map: TileMap) {
+        if let Some((x, y)) = tile_map.spawn {
+            for viewport in &mut self.viewports {
+                let mut position = viewport.camera.frame.initial_point.clone();
+                position[0] = x;
+                position[1] = y;
+                position[2] = 0.5;
+                viewport.camera.set_position(position);
+            }
+        }
+        self.tile_map = Some(tile_map);
+    }
+
     pub fn create_entity(&mut self) -> &mut Entity {
         let entity = Entity::new();
         self.entities.push(entity);
         self.entities.last_mut().unwrap()
     }
 
+    /// Rotates active camera and moves its hyperslice with keyboard.
+    fn handle_camera_controls(&mut self) {
+        if self.console_engine.is_key_pressed(NEXT_VIEWPORT_KEY) {
+            self.active_viewport = (self.active_viewport + 1) % self.viewports.len();
+        }
+        let delta_secs = match &self.clock {
+            Clock::Inactive => return,
+            Clock::Active(active_clock) => active_clock.delta().as_secs_f32(),
+        };
+        let camera = &mut self.viewports[self.active_viewport].camera;
+        let dim = camera.frame.dim();
+
+        for (positive, negative, a, b) in CAMERA_ROTATION_KEYS {
+            if a >= dim || b >= dim {
+                continue;
+            }
+            if self.console_engine.is_key_held(KeyCode::Char(positive)) {
+                camera.rotate(a, b, CAMERA_ROTATION_SPEED * delta_secs).unwrap();
+            }
+            if self.console_engine.is_key_held(KeyCode::Char(negative)) {
+                camera.rotate(a, b, -CAMERA_ROTATION_SPEED * delta_secs).unwrap();
+            }
+        }
+
+        if dim > 3 {
+            let (forward, backward) = HYPERSLICE_KEYS;
+            if self.console_engine.is_key_held(KeyCode::Char(forward)) {
+                camera.hyperslice_offset += HYPERSLICE_SPEED * delta_secs;
+            }
+            if self.console_engine.is_key_held(KeyCode::Char(backward)) {
+                camera.hyperslice_offset -= HYPERSLICE_SPEED * delta_secs;
+            }
+        }
+    }
+
+    /// Moves objects of `spatial_hash` to the current transforms of `game_objects`.
+    fn update_spatial_hash(&mut self) {
+        let boxes: Vec<Option<Aabb>> = self.game_objects
+            .iter()
+            .map(|scene_object| {
+                let scene_object = scene_object.borrow();
+                scene_object.game_object.bounding_box(&scene_object.transform)
+            })
+            .collect();
+        self.spatial_hash.update_all(&boxes);
+    }
+
+    /// Walks active camera along the floor and opens doors in tile map mode.
+    fn handle_tile_map_controls(&mut self) {
+        let delta_secs = match &self.clock {
+            Clock::Inactive => return,
+            Clock::Active(active_clock) => active_clock.delta().as_secs_f32(),
+        };
+        let tile_map = match &mut self.tile_map {
+            None => return,
+            Some(tile_map) => tile_map,
+        };
+        let camera = &mut self.viewports[self.active_viewport].camera;
+        let view = &camera.frame.base[0];
+        let length = (view[0] * view[0] + view[1] * view[1]).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        let forward = (view[0] / length, view[1] / length);
+
+        let (forward_key, backward_key) = WALK_KEYS;
+        let mut step = 0.0;
+        if self.console_engine.is_key_held(KeyCode::Char(forward_key)) {
+            step += WALK_SPEED * delta_secs;
+        }
+        if self.console_engine.is_key_held(KeyCode::Char(backward_key)) {
+            step -= WALK_SPEED * delta_secs;
+        }
+        let mut position = camera.frame.initial_point.clone();
+        // Axes are moved separately, so camera slides along walls.
+        if !tile_map.is_blocked(position[0] + forward.0 * step, position[1]) {
+            position[0] += forward.0 * step;
+        }
+        if !tile_map.is_blocked(position[0], position[1] + forward.1 * step) {
+            position[1] += forward.1 * step;
+        }
+
+        if self.console_engine.is_key_pressed(KeyCode::Char(DOOR_KEY)) {
+            let (x, y) = (position[0] + forward.0, position[1] + forward.1);
+            if x >= 0.0 && y >= 0.0 {
+                let _ = tile_map.toggle_door(x as usize, y as usize);
+            }
+        }
+        camera.set_position(position);
+    }
+
+    /// Draws shaded colours of 3d canvas of viewport at `index` in `config.render_mode`.
+    fn draw_canvas(&mut self, index: usize) {
+        match self.config.render_mode {
+            RenderMode::Ascii => self.draw_canvas_ascii(index),
+            RenderMode::HalfBlock => self.draw_canvas_half_block(index),
+            RenderMode::Braille => self.draw_canvas_braille(index),
+        }
+    }
+
+    /// Draws every pixel as a cell. Brighter points are drawn with denser characters
+    /// of `config.ascii_ramp` unless material has its own glyph. If `config.edge_glyphs` is set,
+    /// edges of objects are drawn with characters along the edge. Misses are left blank.
+    fn draw_canvas_ascii(&mut self, index: usize) {
+        let Viewport { camera, rect, canvas, .. } = &self.viewports[index];
+        let console_colors = Palette::new_from_game_config(&self.config).quantize(&canvas.colors);
+        let ramp: Vec<char> = self.config.ascii_ramp.chars().collect();
+        let (left, up) = (&camera.frame.base[1], &camera.frame.base[2]);
+        for i in 0..canvas.height {
+            for j in 0..canvas.width {
+                // Supersampled pixels on silhouettes may have colour without material and normal.
+                let (char, fg) = match (canvas.colors[i][j], console_colors[i][j]) {
+                    (Some(color), Some(console_color)) => {
+                        let edge = match &canvas.normals[i][j] {
+                            Some(normal) if self.config.edge_glyphs && canvas.is_edge(i, j) => {
+                                edge_glyph(-normal.dot_product(left), normal.dot_product(up))
+                            }
+                            _ => None,
+                        };
+                        let glyph = canvas.materials[i][j].as_ref().and_then(|material| material.glyph);
+                        let char = glyph.or(edge).unwrap_or_else(|| ramp_glyph(&ramp, color.luminance()));
+                        (char, console_color)
+                    }
+                    _ => (' ', Color::Black),
+                };
+                let pixel = Pixel {
+                    bg: Color::Black,
+                    fg,
+                    chr: char,
+                };
+                self.console_engine.set_pxl((rect.x + j) as i32, (rect.y + i) as i32, pixel);
+            }
+        }
+    }
+
+    /// Draws two pixels, one above another, per cell: upper pixel in foreground colour of `▀`
+    /// and lower one in background colour.
+    fn draw_canvas_half_block(&mut self, index: usize) {
+        let Viewport { rect, canvas, .. } = &self.viewports[index];
+        let console_colors = Palette::new_from_game_config(&self.config).quantize(&canvas.colors);
+        let color = |i: usize, j: usize| console_colors[i][j].unwrap_or(Color::Black);
+        for i in 0..canvas.height / 2 {
+            for j in 0..canvas.width {
+                let pixel = Pixel {
+                    bg: color(2 * i + 1, j),
+                    fg: color(2 * i, j),
+                    chr: UPPER_HALF_BLOCK,
+                };
+                self.console_engine.set_pxl((rect.x + j) as i32, (rect.y + i) as i32, pixel);
+            }
+        }
+    }
+
+    /// Draws 2×4 pixels per cell as Braille pattern: pixels brighter than `BRAILLE_THRESHOLD`
+    /// are raised dots. Cell is coloured with the average colour of its dots.
+    fn draw_canvas_braille(&mut self, index: usize) {
+        let Viewport { rect, canvas, .. } = &self.viewports[index];
+        let (height, width) = (canvas.height / 4, canvas.width / 2);
+        let mut cells = Matrix::from_rule(height, width, |_, _| ([[false; 2]; 4], None));
+        for i in 0..height {
+            for j in 0..width {
+                let mut dots = [[false; 2]; 4];
+                let mut sum = Rgb::BLACK;
+                let mut count = 0;
+                for (row, dots_row) in dots.iter_mut().enumerate() {
+                    for (column, dot) in dots_row.iter_mut().enumerate() {
+                        if let Some(color) = canvas.colors[4 * i + row][2 * j + column] {
+                            if color.luminance() > BRAILLE_THRESHOLD {
+                                *dot = true;
+                                sum += color;
+                                count += 1;
+                            }
+                        }
+                    }
+                }
+                let color = match count {
+                    0 => None,
+                    _ => Some(sum * (1.0 / count as f32)),
+                };
+                cells[i][j] = (dots, color);
+            }
+        }
+        // Colours of cells are dithered after averaging.
+        let colors = Matrix::from_rule(height, width, |i, j| cells[i][j].1);
+        let console_colors = Palette::new_from_game_config(&self.config).quantize(&colors);
+        for i in 0..height {
+            for j in 0..width {
+                let pixel = match console_colors[i][j] {
+                    None => pixel::pxl(' '),
+                    Some(color) => pixel::pxl_fg(braille_glyph(&cells[i][j].0), color),
+                };
+                self.console_engine.set_pxl((rect.x + j) as i32, (rect.y + i) as i32, pixel);
+            }
+        }
+    }
+
+    /// Draws wall slices of tile canvas of viewport at `index` between ceiling and floor.
+    /// Walls get darker with distance, walls facing y are darker than walls facing x.
+    fn draw_tile_canvas(&mut self, index: usize, floor_color: Color, ceiling_color: Color) {
+        let Viewport { rect, tile_canvas, .. } = &self.viewports[index];
+        let palette = Palette::new_from_game_config(&self.config);
+        let (floor_color, ceiling_color) = (palette.adapt(floor_color), palette.adapt(ceiling_color));
+        for j in 0..tile_canvas.width {
+            let slice = tile_canvas.columns[j];
+            for i in 0..tile_canvas.height {
+                let pixel = match slice {
+                    Some(slice) if slice.top <= i && i < slice.bottom => {
+                        let side_shade = if slice.hit.side == 0 { 1.0 } else { 0.7 };
+                        let a = side_shade / (1.0 + 0.25 * slice.hit.distance);
+                        Pixel {
+                            bg: Color::Black,
+                            fg: palette.console_color(Rgb::grey(a)),
+                            chr: slice.hit.kind.glyph(),
+                        }
+                    }
+                    Some(slice) if i >= slice.bottom => pixel::pxl_bg(' ', floor_color),
+                    None if 2 * i >= tile_canvas.height => pixel::pxl_bg(' ', floor_color),
+                    _ => pixel::pxl_bg(' ', ceiling_color),
+                };
+                self.console_engine.set_pxl((rect.x + j) as i32, (rect.y + i) as i32, pixel);
+            }
+        }
+    }
+
     fn update(&mut self) -> bool {
         self.console_engine.wait_frame(); // wait for next frame + capture inputs
         if let Clock::Active(active_clock) = &mut self.clock {
@@ -113,35 +479,44 @@ Es: EventSystemT<E> + 'static {
             active_clock.current_frame = Instant::now();
         }
 
+        self.handle_camera_controls();
+        self.handle_tile_map_controls();
+        self.update_spatial_hash();
+
         self.console_engine.clear_screen();
-    
-        let width = self.canvas.width;
-        let height = self.canvas.height;
-        let distances = &self.canvas.distances;
-        let fg = |i, j| {
-            match distances[i][j] {
-                None => Color::Red,
-                Some(dist) => {
-                    let a: u8 = ((dist / self.config.camera_draw_distance) * 255.0) as u8;
-                    Color::Rgb { r: a, g: a, b: a }
+
+        match &self.tile_map {
+            Some(tile_map) => {
+                let (floor_color, ceiling_color) = (tile_map.floor_color, tile_map.ceiling_color);
+                for viewport in &mut self.viewports {
+                    viewport.tile_canvas.update(&viewport.camera, tile_map);
+                }
+                for index in 0..self.viewports.len() {
+                    self.draw_tile_canvas(index, floor_color, ceiling_color);
                 }
             }
-        };
-        for i in 0..height {
-            for j in 0..width {
-                let char = 'O';
-                let char = match distances[i][j] {
-                    None => 'n',
-                    Some(d) => format!("{}", d).chars().nth(0).unwrap(),
-                };
-                let pixel = Pixel {
-                    bg: Color::Black,
-                    fg: fg(i, j),
-                    chr: char,
-                };
-                self.console_engine.set_pxl(i as i32, j as i32, pixel);
+            None => {
+                let scene_objects: Vec<Ref<SceneObject>> = self.game_objects
+                    .iter()
+                    .map(|scene_object| scene_object.borrow())
+                    .collect();
+                let objects: Vec<&SceneObject> = scene_objects
+                    .iter()
+                    .map(|scene_object| &**scene_object)
+                    .collect();
+                let borrowed_lights: Vec<Ref<Light>> = self.lights.iter().map(|light| light.borrow()).collect();
+                let lights: Vec<&Light> = borrowed_lights.iter().map(|light| &**light).collect();
+                for viewport in &mut self.viewports {
+                    viewport.canvas.update(&viewport.camera, &objects, &lights);
+                }
+                drop(borrowed_lights);
+                drop(scene_objects);
+                for index in 0..self.viewports.len() {
+                    self.draw_canvas(index);
+                }
             }
         }
+        let height = self.config.screen_height;
 
         let debug_clock_text: String = match &self.clock {
             Clock::Inactive => 
@@ -162,7 +537,13 @@ Es: EventSystemT<E> + 'static {
         //             "Plane: {:?}",
         //             moving_plane.borrow().0.transform.position),
         // };
-        let debug_plane_text = "commented";
+        let debug_plane_text = match &self.tile_map {
+            Some(_) => String::new(),
+            None => {
+                let canvas = &self.viewports[self.active_viewport].canvas;
+                format!("Culled objects: {}/{}", canvas.culled_count, canvas.objects_count)
+            }
+        };
 
         let debug_text = debug_clock_text.clone() + " " + &debug_plane_text;
         // Debug draw:
@@ -1,4 +1,13 @@
-use ini::Ini;
+use std::str::FromStr;
+
+use ini::{Ini, Properties};
+
+use super::*;
+
+/// Characters of shaded surfaces from the darkest to the brightest.
+pub const DEFAULT_ASCII_RAMP: &str = " .:-=+*#%@";
+/// Ratio of width of terminal cell to its height in usual fonts.
+pub const DEFAULT_CELL_ASPECT_RATIO: f32 = 0.5;
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct GameConfig {
@@ -7,6 +16,42 @@ pub struct GameConfig {
     pub target_fps: usize,
     pub camera_fov: f32,
     pub camera_draw_distance: f32,
+    /// How camera spreads rays over the screen.
+    pub camera_projection: Projection,
+    /// How fog thickens with distance, `FogMode::None` disables fog.
+    pub fog_mode: FogMode,
+    /// Colour which distant surfaces fade to, saved as `"r g b"` with channels from 0 to 1.
+    pub fog_color: Rgb,
+    /// Density of fog per unit of distance.
+    pub fog_density: f32,
+    /// Number of shadow rays cast to every area light, they make soft shadows.
+    pub shadow_samples: usize,
+    /// Offset of shadow rays from surface along its normal, so surfaces do not shadow themselves.
+    pub shadow_bias: f32,
+    /// Maximal depth of rays reflected by mirrors and refracted by transparent materials.
+    pub max_bounces: usize,
+    /// Number of rays per pixel along each side, colour of pixel is averaged over `supersampling²` rays.
+    pub supersampling: usize,
+    /// Supersampling rays go through random points of their subpixels, which turns aliasing into noise.
+    pub jittered_sampling: bool,
+    /// Supersamples only pixels which depth differs strongly from their neighbours.
+    pub adaptive_supersampling: bool,
+    /// Characters which draw brightness of surfaces from the darkest to the brightest.
+    pub ascii_ramp: String,
+    /// Draws edges of objects with `/ \ | _` along the edge instead of `ascii_ramp`.
+    pub edge_glyphs: bool,
+    /// How pixels of 3d canvas are drawn. Half-block and Braille modes render more pixels than
+    /// there are cells on the screen.
+    pub render_mode: RenderMode,
+    /// Colours which terminal can show, `ColorMode::detect` guesses them from environment.
+    pub color_mode: ColorMode,
+    /// How colours missing in `color_mode` are imitated.
+    pub dithering: Dithering,
+    /// Passes which change colours of 3d canvas before they are drawn, applied in order.
+    pub post_processes: Vec<PostProcess>,
+    /// Ratio of width of terminal cell to its height, cells are usually twice as tall as wide.
+    /// `utils::terminal_cell_aspect_ratio` detects it if terminal reports its size in pixels.
+    pub cell_aspect_ratio: f32,
 }
 
 impl GameConfig {
@@ -14,11 +59,44 @@ impl GameConfig {
     pub fn load_from_file(filepath: &str) -> Result<GameConfig, Box<dyn std::error::Error>> {
         let ini = Ini::load_from_file(filepath)?;
         let section = ini.general_section();
+        let default = GameConfig::default();
         let screen_width = section.get("screen_width").ok_or("Missing screen_width")?.parse()?;
         let screen_height = section.get("screen_height").ok_or("Missing screen_height")?.parse()?;
         let target_fps = section.get("target_fps").ok_or("Missing target_fps")?.parse()?;
         let camera_fov = section.get("camera_fov").ok_or("Missing camera_fov")?.parse()?;
         let camera_draw_distance = section.get("camera_draw_distance").ok_or("Missing camera_draw_distance")?.parse()?;
+        let camera_projection = parse_or(section, "camera_projection", default.camera_projection)?;
+        let fog_mode = parse_or(section, "fog_mode", default.fog_mode)?;
+        let fog_color = match section.get("fog_color") {
+            Some(color) => parse_rgb(color)?,
+            None => default.fog_color,
+        };
+        let fog_density = parse_or(section, "fog_density", default.fog_density)?;
+        let shadow_samples = parse_or(section, "shadow_samples", default.shadow_samples)?;
+        let shadow_bias = parse_or(section, "shadow_bias", default.shadow_bias)?;
+        let max_bounces = parse_or(section, "max_bounces", default.max_bounces)?;
+        let supersampling = parse_or(section, "supersampling", default.supersampling)?;
+        let jittered_sampling = parse_or(section, "jittered_sampling", default.jittered_sampling)?;
+        let adaptive_supersampling = parse_or(section, "adaptive_supersampling", default.adaptive_supersampling)?;
+        // Ramp is kept in brackets, because ini trims spaces around values.
+        let ascii_ramp = match section.get("ascii_ramp") {
+            None => default.ascii_ramp.clone(),
+            Some(ramp) => ramp
+                .strip_prefix('[').and_then(|ramp| ramp.strip_suffix(']')).ok_or("ascii_ramp must be in brackets")?
+                .to_string(),
+        };
+        if ascii_ramp.is_empty() {
+            return Err("Empty ascii_ramp".into());
+        }
+        let edge_glyphs = parse_or(section, "edge_glyphs", default.edge_glyphs)?;
+        let render_mode = parse_or(section, "render_mode", default.render_mode)?;
+        let color_mode = parse_or(section, "color_mode", default.color_mode)?;
+        let dithering = parse_or(section, "dithering", default.dithering)?;
+        let post_processes = match section.get("post_processes") {
+            Some(post_processes) => parse_post_processes(post_processes)?,
+            None => default.post_processes.clone(),
+        };
+        let cell_aspect_ratio = parse_or(section, "cell_aspect_ratio", default.cell_aspect_ratio)?;
 
         Ok(GameConfig {
             screen_width,
@@ -26,18 +104,59 @@ impl GameConfig {
             target_fps,
             camera_fov,
             camera_draw_distance,
+            camera_projection,
+            fog_mode,
+            fog_color,
+            fog_density,
+            shadow_samples,
+            shadow_bias,
+            max_bounces,
+            supersampling,
+            jittered_sampling,
+            adaptive_supersampling,
+            ascii_ramp,
+            edge_glyphs,
+            render_mode,
+            color_mode,
+            dithering,
+            post_processes,
+            cell_aspect_ratio,
         })
     }
 
+    /// Returns ratio of the screen width to its height in pixels of terminal, not in cells.
+    pub fn screen_aspect_ratio(&self) -> f32 {
+        self.screen_width as f32 * self.cell_aspect_ratio / self.screen_height as f32
+    }
+
     /// Saves configs to file.
     pub fn save_to_file(&self, filepath: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let post_processes: Vec<String> = self.post_processes.iter().map(|pass| pass.to_string()).collect();
+        let post_processes = post_processes.join(", ");
         let mut ini = Ini::new();
         ini.with_general_section()
             .set("screen_width", &self.screen_width.to_string())
             .set("screen_height", &self.screen_height.to_string())
             .set("target_fps", &self.target_fps.to_string())
             .set("camera_fov", &self.camera_fov.to_string())
-            .set("camera_draw_distance", &self.camera_draw_distance.to_string());
+            .set("camera_draw_distance", &self.camera_draw_distance.to_string())
+            .set("camera_projection", &self.camera_projection.to_string())
+            .set("fog_mode", &self.fog_mode.to_string())
+            .set("fog_color", &format!("{} {} {}", self.fog_color.r, self.fog_color.g, self.fog_color.b))
+            .set("fog_density", &self.fog_density.to_string())
+            .set("shadow_samples", &self.shadow_samples.to_string())
+            .set("shadow_bias", &self.shadow_bias.to_string())
+            .set("max_bounces", &self.max_bounces.to_string())
+            .set("supersampling", &self.supersampling.to_string())
+            .set("jittered_sampling", &self.jittered_sampling.to_string())
+            .set("adaptive_supersampling", &self.adaptive_supersampling.to_string())
+            .set("ascii_ramp", &format!("[{}]", self.ascii_ramp))
+            .set("edge_glyphs", &self.edge_glyphs.to_string())
+            .set("render_mode", &self.render_mode.to_string())
+            .set("color_mode", &self.color_mode.to_string())
+            .set("dithering", &self.dithering.to_string())
+            .set("post_processes", &post_processes)
+            .set("cell_aspect_ratio", &self.cell_aspect_ratio.to_string());
         ini.write_to_file(filepath).unwrap();
         Ok(())
     }
@@ -51,10 +170,63 @@ impl Default for GameConfig {
             target_fps: 30,
             camera_fov: 75.0,
             camera_draw_distance: 100.0,
+            camera_projection: Projection::Perspective,
+            fog_mode: FogMode::None,
+            fog_color: Rgb::BLACK,
+            fog_density: 0.05,
+            shadow_samples: 8,
+            shadow_bias: 1e-3,
+            max_bounces: 3,
+            supersampling: 1,
+            jittered_sampling: false,
+            adaptive_supersampling: false,
+            ascii_ramp: DEFAULT_ASCII_RAMP.to_string(),
+            edge_glyphs: false,
+            render_mode: RenderMode::Ascii,
+            color_mode: ColorMode::TrueColor,
+            dithering: Dithering::None,
+            post_processes: vec![],
+            cell_aspect_ratio: DEFAULT_CELL_ASPECT_RATIO,
         }
     }
 }
 
+/// Parses value of `key`. Missing key gives `default`, so files saved by older versions still load.
+fn parse_or<T: FromStr>(section: &Properties, key: &str, default: T) -> Result<T, Box<dyn std::error::Error>>
+where
+    Box<dyn std::error::Error>: From<T::Err>,
+{
+    match section.get(key) {
+        Some(value) => Ok(value.parse()?),
+        None => Ok(default),
+    }
+}
+
+/// Parses colour from `"r g b"` with non-negative channels, 1 is full intensity.
+fn parse_rgb(text: &str) -> Result<Rgb, Box<dyn std::error::Error>> {
+    let channels = text
+        .split_whitespace()
+        .map(|channel| channel.parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()?;
+    if channels.iter().any(|channel| !channel.is_finite() || *channel < 0.0) {
+        return Err(format!("Colour channels must be finite and non-negative, got `{}`", text.trim()).into());
+    }
+    match channels[..] {
+        [r, g, b] => Ok(Rgb::new(r, g, b)),
+        _ => Err(format!("Expected three colour channels, got `{}`", text.trim()).into()),
+    }
+}
+
+/// Parses comma separated list of post-processes, e.g. `"outline, gamma 2.2"`. Empty list is allowed.
+fn parse_post_processes(text: &str) -> Result<Vec<PostProcess>, String> {
+    text
+        .split(',')
+        .map(|pass| pass.trim())
+        .filter(|pass| !pass.is_empty())
+        .map(|pass| pass.parse())
+        .collect()
+}
+
 #[cfg(test)]
 mod game_config_tests {
     use std::fs;
@@ -102,4 +274,83 @@ mod game_config_tests {
         remove_file("a.ini");
         remove_file("b.ini");
     }
+
+    #[test]
+    fn load_original_keys() {
+        let filename = "original_keys.ini";
+        fs::write(
+            filename,
+            "screen_width=80\nscreen_height=24\ntarget_fps=60\ncamera_fov=1.5\ncamera_draw_distance=20\n",
+        ).expect("Could not write file.");
+        let load = GameConfig::load_from_file(filename).expect("Could not read from file.");
+        remove_file(filename);
+        let expected = GameConfig {
+            screen_width: 80,
+            screen_height: 24,
+            target_fps: 60,
+            camera_fov: 1.5,
+            camera_draw_distance: 20.0,
+            ..GameConfig::default()
+        };
+        assert_eq!(load, expected);
+    }
+
+    #[test]
+    fn glyph_settings() {
+        let filename = "ascii_ramp.ini";
+        let config = GameConfig {
+            ascii_ramp: String::from(" .#; "),
+            edge_glyphs: true,
+            render_mode: RenderMode::Braille,
+            color_mode: ColorMode::Xterm256,
+            dithering: Dithering::ErrorDiffusion,
+            ..GameConfig::default()
+        };
+        config.save_to_file(filename).expect("Could not save to file.");
+        let load = GameConfig::load_from_file(filename).expect("Could not read from file.");
+        remove_file(filename);
+        assert_eq!(load, config);
+    }
+
+    #[test]
+    fn fog_settings() {
+        let filename = "fog.ini";
+        let config = GameConfig {
+            fog_mode: FogMode::ExponentialSquared,
+            fog_color: Rgb::new(0.1, 0.7, 1.0 / 3.0),
+            fog_density: 0.25,
+            ..GameConfig::default()
+        };
+        config.save_to_file(filename).expect("Could not save to file.");
+        let load = GameConfig::load_from_file(filename).expect("Could not read from file.");
+        remove_file(filename);
+        assert_eq!(load, config);
+        assert!(parse_rgb("1 2").is_err());
+        assert_eq!(parse_rgb("0.5 0.5 0.5").unwrap(), Rgb::grey(0.5));
+        assert!(parse_rgb("0.5 0.5 -1").is_err());
+        assert!(parse_rgb("0.5 inf 0.5").is_err());
+    }
+
+    #[test]
+    fn post_process_settings() {
+        let filename = "post_processes.ini";
+        let config = GameConfig {
+            post_processes: vec![
+                PostProcess::Outline,
+                PostProcess::AmbientOcclusion { strength: 0.5 },
+                PostProcess::Gamma { gamma: 2.2 },
+            ],
+            ..GameConfig::default()
+        };
+        config.save_to_file(filename).expect("Could not save to file.");
+        let load = GameConfig::load_from_file(filename).expect("Could not read from file.");
+        remove_file(filename);
+        assert_eq!(load, config);
+        assert_eq!(parse_post_processes(""), Ok(vec![]));
+        assert_eq!(
+            parse_post_processes("outline,, gamma 2"),
+            Ok(vec![PostProcess::Outline, PostProcess::Gamma { gamma: 2.0 }])
+        );
+        assert!(parse_post_processes("outline, vignette 2").is_err());
+    }
 }
@@ -1,7 +1,91 @@
 use crate::math::*;
 use super::*;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 
 pub trait GameObject {
     fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32>;
-}
\ No newline at end of file
+
+    /// Returns sorted disjoint intervals of ray parameter where ray is inside of the object.
+    /// Unlike `intersection_distance` intervals behind the ray point are returned too.
+    /// 
+    /// Default implementation treats object as infinitely thin surface: 
+    /// the only interval is the point of the nearest intersection.
+    fn intersection_intervals(&self, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+        match self.intersection_distance(transform, ray) {
+            None => vec![],
+            Some(t) => vec![Interval::new(t, t)],
+        }
+    }
+
+    /// Returns outward unit normal of the surface at `point` which lies on the surface.
+    /// Returns `None` if normal is unknown.
+    fn normal(&self, _transform: &Transform, _point: &Vector<f32>) -> Option<Vector<f32>> {
+        None
+    }
+
+    /// Returns texture coordinates `(u, v)` of `point` which lies on the surface.
+    /// Returns `None` if object has no parametrisation, then texture is not applied.
+    fn uv(&self, _transform: &Transform, _point: &Vector<f32>) -> Option<(f32, f32)> {
+        None
+    }
+
+    /// Returns box in world coordinates which contains the whole object.
+    /// Returns `None` if object is unbounded, like `Hyperplane`.
+    fn bounding_box(&self, _transform: &Transform) -> Option<Aabb> {
+        None
+    }
+}
+
+/// Returns distance to the nearest boundary of `intervals` which is not behind the ray point.
+/// If ray point is inside of an interval returns distance to its exit.
+pub fn nearest_interval_distance(intervals: &[Interval]) -> Option<f32> {
+    intervals
+        .iter()
+        .find(|interval| interval.exit >= 0.0)
+        .map(|interval| if interval.enter >= 0.0 { interval.enter } else { interval.exit })
+        .filter(|t| t.is_finite())
+}
+
+/// Shared game object, so gameplay code can keep a handle and change the object between frames,
+/// e.g. `set_voxel` of `VoxelGrid` added to the scene as `Box::new(grid.clone())`.
+impl<T: GameObject> GameObject for Rc<RefCell<T>> {
+    fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        self.borrow().intersection_distance(transform, ray)
+    }
+
+    fn intersection_intervals(&self, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+        self.borrow().intersection_intervals(transform, ray)
+    }
+
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        self.borrow().normal(transform, point)
+    }
+
+    fn uv(&self, transform: &Transform, point: &Vector<f32>) -> Option<(f32, f32)> {
+        self.borrow().uv(transform, point)
+    }
+
+    fn bounding_box(&self, transform: &Transform) -> Option<Aabb> {
+        self.borrow().bounding_box(transform)
+    }
+}
+
+/// Game object placed in the game world.
+pub struct SceneObject {
+    pub transform: Transform,
+    pub game_object: Box<dyn GameObject>,
+    pub material: Material,
+}
+
+impl SceneObject {
+    /// Creates object with default material.
+    pub fn new(transform: Transform, game_object: Box<dyn GameObject>) -> SceneObject {
+        SceneObject::with_material(transform, game_object, Material::default())
+    }
+
+    pub fn with_material(transform: Transform, game_object: Box<dyn GameObject>, material: Material) -> SceneObject {
+        SceneObject { transform, game_object, material }
+    }
+}
@@ -0,0 +1,84 @@
+/// Character which upper half is drawn in foreground colour and lower half in background colour.
+pub const UPPER_HALF_BLOCK: char = '▀';
+/// Bits of Braille dots by rows and columns of 2×4 cell.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Normals which projection onto the screen is shorter than this face the camera and do not make edge glyphs.
+const MIN_EDGE_PROJECTION: f32 = 0.3;
+
+/// Returns character of `ramp` for brightness `luminance` in `[0, 1]`,
+/// the first character is for black and the last one for white.
+/// Empty ramp gives space.
+pub fn ramp_glyph(ramp: &[char], luminance: f32) -> char {
+    if ramp.is_empty() {
+        return ' ';
+    }
+    let level = (luminance.clamp(0.0, 1.0) * (ramp.len() - 1) as f32).round();
+    ramp[level as usize]
+}
+
+/// Returns one of `/ \ | _` which goes along an edge of surface with normal
+/// projected onto the screen as `(right, up)`.
+/// If normal faces the camera there is no visible edge direction and `None` is returned.
+pub fn edge_glyph(right: f32, up: f32) -> Option<char> {
+    if right.hypot(up) < MIN_EDGE_PROJECTION {
+        return None;
+    }
+    // Angle of normal in degrees in `[0, 180)`, edge is perpendicular to normal.
+    let angle = up.atan2(right).to_degrees().rem_euclid(180.0);
+    Some(match angle {
+        a if !(22.5..157.5).contains(&a) => '|',
+        a if a < 67.5 => '\\',
+        a if a < 112.5 => '_',
+        _ => '/',
+    })
+}
+
+/// Returns Braille pattern with raised dots `dots[row][column]`.
+pub fn braille_glyph(dots: &[[bool; 2]; 4]) -> char {
+    let mut code = 0x2800;
+    for row in 0..4 {
+        for column in 0..2 {
+            if dots[row][column] {
+                code |= BRAILLE_DOTS[row][column];
+            }
+        }
+    }
+    char::from_u32(code).unwrap()
+}
+
+
+#[cfg(test)]
+mod glyph_tests {
+    use super::*;
+
+    #[test]
+    fn ramp() {
+        let ramp: Vec<char> = " .:@".chars().collect();
+        assert_eq!(ramp_glyph(&ramp, 0.0), ' ');
+        assert_eq!(ramp_glyph(&ramp, 0.4), '.');
+        assert_eq!(ramp_glyph(&ramp, 0.6), ':');
+        assert_eq!(ramp_glyph(&ramp, 5.0), '@');
+        assert_eq!(ramp_glyph(&[], 0.5), ' ');
+    }
+
+    #[test]
+    fn edges() {
+        assert_eq!(edge_glyph(1.0, 0.0), Some('|'));
+        assert_eq!(edge_glyph(-1.0, 0.1), Some('|'));
+        assert_eq!(edge_glyph(0.0, 1.0), Some('_'));
+        assert_eq!(edge_glyph(0.0, -1.0), Some('_'));
+        assert_eq!(edge_glyph(0.7, 0.7), Some('\\'));
+        assert_eq!(edge_glyph(-0.7, -0.7), Some('\\'));
+        assert_eq!(edge_glyph(-0.7, 0.7), Some('/'));
+        assert_eq!(edge_glyph(0.1, 0.1), None);
+    }
+
+    #[test]
+    fn braille() {
+        assert_eq!(braille_glyph(&[[false; 2]; 4]), '⠀');
+        assert_eq!(braille_glyph(&[[true; 2]; 4]), '⣿');
+        assert_eq!(braille_glyph(&[[true, false], [false, false], [false, false], [false, true]]), '⢁');
+        assert_eq!(braille_glyph(&[[false, false], [false, true], [true, false], [false, false]]), '⠔');
+    }
+}
@@ -0,0 +1,414 @@
+use std::fs;
+
+use super::*;
+use crate::math::*;
+use crate::utils::next_netpbm_token;
+
+
+/// Terrain made of a grid of heights.
+///
+/// Node `(i, j)` of the grid is the point `(i, j, heights[i][j])` in local coordinates,
+/// so the terrain lies in xy plane and z axis goes up.
+/// Each grid cell is made of two triangles.
+/// Terrain is placed at `transform.position` and scaled by `transform.scale`.
+#[derive(Debug, Clone)]
+pub struct Heightmap {
+    heights: Matrix<f32>,
+    min_height: f32,
+    max_height: f32,
+}
+
+impl Heightmap {
+    /// Creates heightmap from heights of grid nodes.
+    /// Grid must have at least 2 rows and 2 columns, otherwise returns `None`.
+    pub fn new(heights: Matrix<f32>) -> Option<Heightmap> {
+        if heights.rows_count() < 2 || heights.cols_count() < 2 {
+            return None;
+        }
+        let mut min_height = f32::INFINITY;
+        let mut max_height = f32::NEG_INFINITY;
+        for i in 0..heights.rows_count() {
+            for j in 0..heights.cols_count() {
+                min_height = f32::min(min_height, heights[i][j]);
+                max_height = f32::max(max_height, heights[i][j]);
+            }
+        }
+        Some(Heightmap { heights, min_height, max_height })
+    }
+
+    /// Parses plain text grid: each line is a row of heights separated by whitespaces.
+    /// Empty lines are skipped.
+    pub fn from_text_grid(text: &str) -> Result<Heightmap, Box<dyn std::error::Error>> {
+        let mut rows = vec![];
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let row = line
+                .split_whitespace()
+                .map(|height| height.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()?;
+            rows.push(Vector::new(row));
+        }
+        let heights = Matrix::from_rows(rows).ok_or("Rows of grid have different length")?;
+        Ok(Heightmap::new(heights).ok_or("Grid must be at least 2x2")?)
+    }
+
+    /// Parses PGM image, both plain (`P2`) and binary (`P5`) formats are supported.
+    /// Brightness of pixel from black to white becomes height from `0` to `max_height`.
+    /// Image row is `i` and image column is `j` index of the grid.
+    pub fn from_pgm(bytes: &[u8], max_height: f32) -> Result<Heightmap, Box<dyn std::error::Error>> {
+        let mut position = 0;
+        let magic = next_netpbm_token(bytes, &mut position).ok_or("Missing PGM magic number")?;
+        let width: usize = next_netpbm_token(bytes, &mut position).ok_or("Missing PGM width")?.parse()?;
+        let height: usize = next_netpbm_token(bytes, &mut position).ok_or("Missing PGM height")?.parse()?;
+        let max_value: u32 = next_netpbm_token(bytes, &mut position).ok_or("Missing PGM max value")?.parse()?;
+        if max_value == 0 || max_value > 65535 {
+            return Err("PGM max value must be from 1 to 65535".into());
+        }
+        let count = width.checked_mul(height).ok_or("PGM image is too large")?;
+
+        let values: Vec<u32> = match magic.as_str() {
+            "P2" => (0..count)
+                .map(|_| next_netpbm_token(bytes, &mut position).ok_or("Not enough PGM pixels"))
+                .map(|token| Ok(token?.parse::<u32>()?))
+                .collect::<Result<Vec<u32>, Box<dyn std::error::Error>>>()?,
+            "P5" => {
+                // Single whitespace separates header from raster.
+                let raster = bytes.get(position + 1..).ok_or("Not enough PGM pixels")?;
+                let bytes_per_value = if max_value < 256 { 1 } else { 2 };
+                if raster.len() / bytes_per_value < count {
+                    return Err("Not enough PGM pixels".into());
+                }
+                raster
+                    .chunks(bytes_per_value)
+                    .take(count)
+                    .map(|chunk| chunk.iter().fold(0, |value, byte| value * 256 + *byte as u32))
+                    .collect()
+            }
+            _ => return Err(format!("Unsupported PGM format {}", magic).into()),
+        };
+
+        let heights = Matrix::from_rule(height, width, |i, j|
+            values[i * width + j] as f32 / max_value as f32 * max_height);
+        Ok(Heightmap::new(heights).ok_or("Image must be at least 2x2")?)
+    }
+
+    /// Loads heightmap from PGM image file, see `from_pgm`.
+    pub fn load_pgm(filepath: &str, max_height: f32) -> Result<Heightmap, Box<dyn std::error::Error>> {
+        Heightmap::from_pgm(&fs::read(filepath)?, max_height)
+    }
+
+    /// Loads heightmap from plain text grid file, see `from_text_grid`.
+    pub fn load_text_grid(filepath: &str) -> Result<Heightmap, Box<dyn std::error::Error>> {
+        Heightmap::from_text_grid(&fs::read_to_string(filepath)?)
+    }
+
+    pub fn heights(&self) -> &Matrix<f32> {
+        &self.heights
+    }
+
+    /// Returns cell which contains local point `(x, y)` and coordinates of point inside the cell.
+    /// Points out of the grid are clamped to the border cells.
+    fn cell(&self, x: f32, y: f32) -> (usize, usize, f32, f32) {
+        let i = (x.floor().max(0.0) as usize).min(self.heights.rows_count() - 2);
+        let j = (y.floor().max(0.0) as usize).min(self.heights.cols_count() - 2);
+        (i, j, x - i as f32, y - j as f32)
+    }
+
+    /// Returns bilinearly interpolated height at local point `(x, y)`.
+    pub fn height_at(&self, x: f32, y: f32) -> f32 {
+        let (i, j, u, v) = self.cell(x, y);
+        let h = &self.heights;
+        (1.0 - u) * (1.0 - v) * h[i][j] + u * (1.0 - v) * h[i + 1][j] +
+            (1.0 - u) * v * h[i][j + 1] + u * v * h[i + 1][j + 1]
+    }
+
+    /// Returns unit normal of grid node found with central differences of heights.
+    fn node_normal(&self, i: usize, j: usize) -> Vector<f32> {
+        let h = &self.heights;
+        let (i0, i1) = (i.saturating_sub(1), usize::min(i + 1, h.rows_count() - 1));
+        let (j0, j1) = (j.saturating_sub(1), usize::min(j + 1, h.cols_count() - 1));
+        let dh_dx = (h[i1][j] - h[i0][j]) / (i1 - i0) as f32;
+        let dh_dy = (h[i][j1] - h[i][j0]) / (j1 - j0) as f32;
+        let mut normal = Vector::from_xyz(-dh_dx, -dh_dy, 1.0);
+        normal.normalize();
+        normal
+    }
+
+    /// Returns unit normal at local point `(x, y)`:
+    /// normals of four nodes of the cell are bilinearly interpolated,
+    /// so lighting changes smoothly between cells and terrain does not look faceted.
+    pub fn normal_at(&self, x: f32, y: f32) -> Vector<f32> {
+        let (i, j, u, v) = self.cell(x, y);
+        let weighted = |i, j, weight: f32| &self.node_normal(i, j) * weight;
+        let mut normal = (
+            (weighted(i, j, (1.0 - u) * (1.0 - v)) + weighted(i + 1, j, u * (1.0 - v))).unwrap() +
+            (weighted(i, j + 1, (1.0 - u) * v) + weighted(i + 1, j + 1, u * v)).unwrap()
+        ).unwrap();
+        normal.normalize();
+        normal
+    }
+
+    fn node(&self, i: usize, j: usize) -> [f32; 3] {
+        [i as f32, j as f32, self.heights[i][j]]
+    }
+
+    /// Returns the nearest non-negative intersection with two triangles of cell `(i, j)`.
+    fn cell_intersection(&self, i: usize, j: usize, origin: &[f32; 3], direction: &[f32; 3]) -> Option<f32> {
+        let (p00, p10) = (self.node(i, j), self.node(i + 1, j));
+        let (p01, p11) = (self.node(i, j + 1), self.node(i + 1, j + 1));
+        let t1 = triangle_intersection(origin, direction, &p00, &p10, &p11).filter(|t| *t >= 0.0);
+        let t2 = triangle_intersection(origin, direction, &p00, &p11, &p01).filter(|t| *t >= 0.0);
+        match (t1, t2) {
+            (Some(t1), Some(t2)) => Some(f32::min(t1, t2)),
+            (t1, t2) => t1.or(t2),
+        }
+    }
+
+    /// Returns parameters where ray enters and leaves the bounding box of terrain.
+    fn bounding_box_intersection(&self, origin: &[f32; 3], direction: &[f32; 3]) -> Option<(f32, f32)> {
+        let min = [0.0, 0.0, self.min_height];
+        let max = [
+            (self.heights.rows_count() - 1) as f32,
+            (self.heights.cols_count() - 1) as f32,
+            self.max_height,
+        ];
+        let mut enter = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (min[axis] - origin[axis]) / direction[axis];
+            let t2 = (max[axis] - origin[axis]) / direction[axis];
+            enter = f32::max(enter, f32::min(t1, t2));
+            exit = f32::min(exit, f32::max(t1, t2));
+        }
+        if enter > exit {
+            return None;
+        }
+        Some((enter, exit))
+    }
+}
+
+impl GameObject for Heightmap {
+    /// Walks cells of the grid along the ray projection on xy plane
+    /// and tests two triangles of each cell.
+    /// Returns the first intersection which is not behind the ray point.
+    fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        if ray.point.dim() != 3 || ray.direction.dim() != 3 || transform.dim() != 3 {
+            return None;
+        }
+        let ray = transform.to_local_ray(ray)?;
+        let origin = [ray.point[0], ray.point[1], ray.point[2]];
+        let direction = [ray.direction[0], ray.direction[1], ray.direction[2]];
+
+        let (enter, exit) = self.bounding_box_intersection(&origin, &direction)?;
+        if exit < 0.0 {
+            return None;
+        }
+        let start = f32::max(enter, 0.0);
+        let (mut i, mut j, _, _) = self.cell(origin[0] + start * direction[0], origin[1] + start * direction[1]);
+
+        // Digital differential analyzer: parameters where ray crosses the next cell border
+        // and parameter step between borders along each axis.
+        let next_border = |cell: usize, axis: usize| {
+            if direction[axis] > 0.0 {
+                ((cell + 1) as f32 - origin[axis]) / direction[axis]
+            } else if direction[axis] < 0.0 {
+                (cell as f32 - origin[axis]) / direction[axis]
+            } else {
+                f32::INFINITY
+            }
+        };
+        let mut t_max_x = next_border(i, 0);
+        let mut t_max_y = next_border(j, 1);
+        let t_delta_x = 1.0 / direction[0].abs();
+        let t_delta_y = 1.0 / direction[1].abs();
+
+        loop {
+            if let Some(t) = self.cell_intersection(i, j, &origin, &direction) {
+                return Some(t);
+            }
+            if f32::min(t_max_x, t_max_y) > exit {
+                return None;
+            }
+            if t_max_x < t_max_y {
+                if direction[0] > 0.0 { i += 1 } else if i == 0 { return None } else { i -= 1 }
+                t_max_x += t_delta_x;
+            } else {
+                if direction[1] > 0.0 { j += 1 } else if j == 0 { return None } else { j -= 1 }
+                t_max_y += t_delta_y;
+            }
+            if i + 1 >= self.heights.rows_count() || j + 1 >= self.heights.cols_count() {
+                return None;
+            }
+        }
+    }
+
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        let point = transform.to_local_point(point)?;
+        if point.dim() != 3 {
+            return None;
+        }
+        Some(self.normal_at(point[0], point[1]))
+    }
+
+    fn bounding_box(&self, transform: &Transform) -> Option<Aabb> {
+        let local_box = Aabb::new(
+            Vector::from_xyz(0.0, 0.0, self.min_height),
+            Vector::from_xyz(
+                (self.heights.rows_count() - 1) as f32,
+                (self.heights.cols_count() - 1) as f32,
+                self.max_height,
+            ),
+        )?;
+        transform.to_world_box(&local_box)
+    }
+}
+
+/// Möller–Trumbore ray-triangle intersection.
+/// Returns parameter of ray where it intersects triangle `abc`.
+fn triangle_intersection(
+    origin: &[f32; 3],
+    direction: &[f32; 3],
+    a: &[f32; 3],
+    b: &[f32; 3],
+    c: &[f32; 3],
+) -> Option<f32> {
+    let sub = |x: &[f32; 3], y: &[f32; 3]| [x[0] - y[0], x[1] - y[1], x[2] - y[2]];
+    let cross = |x: &[f32; 3], y: &[f32; 3]| [
+        x[1] * y[2] - x[2] * y[1],
+        x[2] * y[0] - x[0] * y[2],
+        x[0] * y[1] - x[1] * y[0],
+    ];
+    let dot = |x: &[f32; 3], y: &[f32; 3]| x[0] * y[0] + x[1] * y[1] + x[2] * y[2];
+
+    let edge1 = sub(b, a);
+    let edge2 = sub(c, a);
+    let p = cross(direction, &edge2);
+    let determinant = dot(&edge1, &p);
+    if determinant.abs() < 1e-9 {
+        return None;
+    }
+    let s = sub(origin, a);
+    let u = dot(&s, &p) / determinant;
+    if !(-1e-6..=1.0 + 1e-6).contains(&u) {
+        return None;
+    }
+    let q = cross(&s, &edge1);
+    let v = dot(direction, &q) / determinant;
+    if v < -1e-6 || u + v > 1.0 + 1e-6 {
+        return None;
+    }
+    Some(dot(&edge2, &q) / determinant)
+}
+
+
+#[cfg(test)]
+mod heightmap_tests {
+    use crate::utils::{assert_eq_f32, assert_eq_option_f32};
+
+    use super::*;
+
+    /// 3x3 grid with a peak of height 2 in the middle.
+    fn peak() -> Heightmap {
+        Heightmap::from_text_grid("
+            0 0 0
+            0 2 0
+            0 0 0
+        ").unwrap()
+    }
+
+    fn test(heightmap: &Heightmap, transform: &Transform, point: [f32; 3], direction: [f32; 3], expected: Option<f32>) {
+        let ray = Ray::new(Vector::new(point.to_vec()), Vector::new(direction.to_vec()));
+        assert_eq_option_f32(heightmap.intersection_distance(transform, &ray), expected);
+    }
+
+    #[test]
+    fn text_grid_different_rows() {
+        assert!(Heightmap::from_text_grid("0 0\n0 0 0").is_err());
+        assert!(Heightmap::from_text_grid("0 0").is_err());
+        assert!(Heightmap::from_text_grid("0 a\n0 0").is_err());
+    }
+
+    #[test]
+    fn plain_pgm() {
+        let pgm = b"P2\n# comment\n3 2\n4\n0 1 2\n3 4 0\n";
+        let h = Heightmap::from_pgm(pgm, 2.0).unwrap();
+        assert_eq!(h.heights(), &Matrix::new(2, 3, vec![0.0, 0.5, 1.0, 1.5, 2.0, 0.0]).unwrap());
+    }
+
+    #[test]
+    fn binary_pgm() {
+        let mut pgm = b"P5 2 2 255\n".to_vec();
+        pgm.extend([0, 255, 51, 102]);
+        let h = Heightmap::from_pgm(&pgm, 1.0).unwrap();
+        assert_eq!(h.heights(), &Matrix::new(2, 2, vec![0.0, 1.0, 0.2, 0.4]).unwrap());
+    }
+
+    #[test]
+    fn binary_pgm_too_short() {
+        let mut pgm = b"P5 2 2 255\n".to_vec();
+        pgm.extend([0, 255, 51]);
+        assert!(Heightmap::from_pgm(&pgm, 1.0).is_err());
+    }
+
+    #[test]
+    fn oversized_pgm() {
+        assert!(Heightmap::from_pgm(b"P5 9223372036854775807 3 255\n\0", 1.0).is_err());
+        assert!(Heightmap::from_pgm(b"P2 9223372036854775807 3 255\n0", 1.0).is_err());
+    }
+
+    #[test]
+    fn ray_from_above() {
+        test(&peak(), &Transform::default(), [1.0, 1.0, 10.0], [0.0, 0.0, -1.0], Some(8.0));
+        test(&peak(), &Transform::default(), [0.5, 1.0, 10.0], [0.0, 0.0, -1.0], Some(9.0));
+    }
+
+    #[test]
+    fn ray_along_ground() {
+        // Ray goes along x at height 1 and hits slope of the peak at x = 0.5.
+        test(&peak(), &Transform::default(), [-5.0, 1.0, 1.0], [1.0, 0.0, 0.0], Some(5.5));
+        // Ray goes above the peak.
+        test(&peak(), &Transform::default(), [-5.0, 1.0, 2.5], [1.0, 0.0, 0.0], None);
+    }
+
+    #[test]
+    fn ray_diagonal() {
+        test(&peak(), &Transform::default(), [-1.0, -1.0, 1.0], [1.0, 1.0, 0.0], Some(1.5));
+    }
+
+    #[test]
+    fn ray_away() {
+        test(&peak(), &Transform::default(), [1.0, 1.0, 10.0], [0.0, 0.0, 1.0], None);
+        test(&peak(), &Transform::default(), [5.0, 1.0, 1.0], [1.0, 0.0, 0.0], None);
+    }
+
+    #[test]
+    fn transform_placement_and_scale() {
+        let mut t = Transform::new_from_coords(10.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        t.scale = 2.0;
+        // Peak is at (12, 2, 4) in the world.
+        test(&peak(), &t, [12.0, 2.0, 10.0], [0.0, 0.0, -1.0], Some(6.0));
+    }
+
+    #[test]
+    fn height_and_normal() {
+        let h = Heightmap::from_text_grid("0 0\n1 1").unwrap();
+        assert_eq_f32(h.height_at(0.5, 0.5), 0.5);
+        let n = h.normal_at(0.3, 0.7);
+        let expected = &Vector::from_xyz(-1.0, 0.0, 1.0) / 2.0_f32.sqrt();
+        assert!(n.approximately_equal(&expected, 1e-5), "{:?}", n);
+    }
+
+    #[test]
+    fn normal_is_smooth() {
+        let h = peak();
+        let left = h.normal_at(0.999, 0.5);
+        let right = h.normal_at(1.001, 0.5);
+        assert!(left.approximately_equal(&right, 1e-2));
+        assert!(h.normal_at(1.0, 1.0).approximately_equal(&Vector::from_xyz(0.0, 0.0, 1.0), 1e-5));
+    }
+}
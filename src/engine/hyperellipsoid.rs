@@ -1,60 +1,113 @@
 use crate::math::*;
 use super::*;
-use crate::utils::assert_eq_f32;
+use crate::utils::solve_quadratic;
 
+/// Hyperellipsoid `sum(x_i^2 / a_i^2) = 1` where `a_i` are `semiaxes`.
+/// Dimension of space is the number of semiaxes.
 #[derive(Debug, Clone)]
 pub struct Hyperellipsoid {
     pub semiaxes: Vec<f32>
 }
 
 impl Hyperellipsoid {
+    pub fn new(semiaxes: Vec<f32>) -> Self {
+        Self { semiaxes }
+    }
+
     pub fn new3d(a: f32, b: f32, c: f32) -> Self {
         Self{ semiaxes: vec![a, b, c] }
     }
+
+    /// Returns dimension of space where hyperellipsoid lives.
+    pub fn dim(&self) -> usize {
+        self.semiaxes.len()
+    }
 }
 
-impl GameObject for Hyperellipsoid {
+impl Hyperellipsoid {
+    /// Returns parameters of ray where it intersects hyperellipsoid in ascending order.
     /// https://math.stackexchange.com/questions/3309397/line-ellipsoid-intersection
+    ///
+    /// If dimensions of `ray`, `transform` and `self.semiaxes` are not the same returns `None`.
+    fn intersection_parameters(&self, transform: &Transform, ray: &Ray) -> Option<(f32, f32)> {
+        if ray.point.dim() != self.dim() || ray.direction.dim() != self.dim() ||
+            transform.position.dim() != self.dim() {
+            return None;
+        }
+
+        // Ray relative to the center and scale of hyperellipsoid.
+        let ray = transform.to_local_ray(ray)?;
+        let x0 = &ray.point;
+
+        // Substitute x0 + t * direction into sum(x_i^2 / a_i^2) = 1:
+        // lambda1 * t^2 + lambda2 * t + lambda3 = 0.
+        let mut lambda1 = 0.0;
+        let mut lambda2 = 0.0;
+        let mut lambda3 = -1.0;
+        for i in 0..self.dim() {
+            let a2 = self.semiaxes[i] * self.semiaxes[i];
+            lambda1 += ray.direction[i] * ray.direction[i] / a2;
+            lambda2 += 2.0 * x0[i] * ray.direction[i] / a2;
+            lambda3 += x0[i] * x0[i] / a2;
+        }
+
+        solve_quadratic(lambda1, lambda2, lambda3)
+    }
+}
+
+impl GameObject for Hyperellipsoid {
+    /// If dimensions of `ray`, `transform` and `self.semiaxes` are not the same returns `None`.
     fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
-        if ray.direction.dim() != 3 || ray.point.dim() != 3 || ray.direction.dim() != 3 || self.semiaxes.len() != 3 {
-            panic!("Not 3d");
+        let (dist1, dist2) = self.intersection_parameters(transform, ray)?;
+        if dist1 >= 0.0 {
+            return Some(dist1);
         }
+        if dist2 >= 0.0 {
+            return Some(dist2);
+        }
+        None
+    }
 
-        let (a, b, c) = (self.semiaxes[0], self.semiaxes[1], self.semiaxes[2]);
-        let x0 = (ray.point.clone() - (Vector::zero3() - transform.position.clone()).unwrap()).unwrap();
-        let (x0, y0, z0) = (x0[0], x0[1], x0[2]);
-
-        let (alpha, beta, gamma) = (ray.direction[0], ray.direction[1], ray.direction[2]);
-
-        let lambda1 = 
-            alpha * alpha * b * b * c * c +
-            beta * beta * a * a * c * c +
-            gamma * gamma * a * a * b * b; 
-        let lambda2 = 
-            (x0 * alpha * b * b * c * c) +
-            (y0 * beta * a * a * c * c) + 
-            (z0 * gamma * a * a * b * b);
-        let lambda3 =
-            x0 * x0 * b * b * c * c +
-            y0 * y0 * a * a * c * c +
-            z0 * z0 * a * a * b * b -
-            a * a * b * b * c * c;
-        let discriminant = lambda2 * lambda2 - lambda1 * lambda3;
-        if discriminant < 0.0 {
-            return None;
+    fn intersection_intervals(&self, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+        match self.intersection_parameters(transform, ray) {
+            None => vec![],
+            Some((enter, exit)) => vec![Interval::new(enter, exit)],
         }
-        let dist1 = (-lambda2 + discriminant.sqrt()) / lambda1;
-        let dist2 = (-lambda2 - discriminant.sqrt()) / lambda1;
-        if dist1 < 0.0 && dist2 < 0.0 {
+    }
+
+    fn bounding_box(&self, transform: &Transform) -> Option<Aabb> {
+        let max: Vec<f32> = self.semiaxes.iter().map(|a| a.abs()).collect();
+        let min = max.iter().map(|a| -a).collect();
+        transform.to_world_box(&Aabb::new(Vector::new(min), Vector::new(max))?)
+    }
+
+    /// Normal is the gradient of `x1^2 / a1^2 + ... + xn^2 / an^2`.
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        let point = transform.to_local_point(point)?;
+        if point.dim() != self.dim() {
             return None;
         }
-        if dist1 < 0.0 {
-            return Some(dist2);
+        let mut normal = Vector::new(
+            (0..self.dim()).map(|i| point[i] / (self.semiaxes[i] * self.semiaxes[i])).collect()
+        );
+        normal.normalize().then_some(normal)
+    }
+
+    /// Spherical mapping over the first three axes: `u` is longitude around the third axis
+    /// from 0 to 1 and `v` is latitude from 0 at the positive end of the third axis to 1 at the negative one.
+    fn uv(&self, transform: &Transform, point: &Vector<f32>) -> Option<(f32, f32)> {
+        let point = transform.to_local_point(point)?;
+        if point.dim() != self.dim() {
+            return None;
         }
-        if dist2 < 0.0 {
-            return Some(dist1);
+        // Direction from the centre on the unit sphere the hyperellipsoid is stretched from.
+        let mut d = Vector::new((0..3).map(|i| if i < self.dim() { point[i] / self.semiaxes[i] } else { 0.0 }).collect());
+        if !d.normalize() {
+            return None;
         }
-        return Some(f32::min(dist1, dist2));
+        let u = 0.5 + d[1].atan2(d[0]) / (2.0 * std::f32::consts::PI);
+        let v = d[2].clamp(-1.0, 1.0).acos() / std::f32::consts::PI;
+        Some((u, v))
     }
 }
 
@@ -62,7 +115,7 @@ impl GameObject for Hyperellipsoid {
 
 #[cfg(test)]
 mod hyperellipsoid_tests {
-    use crate::utils::assert_eq_option_f32;
+    use crate::utils::{assert_eq_f32, assert_eq_option_f32};
 
     use super::*;
 
@@ -103,4 +156,130 @@ mod hyperellipsoid_tests {
             Some(0.0),
         )
     }
-}
\ No newline at end of file
+
+    fn test_nd(
+        semiaxes: Vec<f32>,
+        position: Vec<f32>,
+        point: Vec<f32>,
+        direction: Vec<f32>,
+        expected_distance: Option<f32>,
+    ) {
+        let e = Hyperellipsoid::new(semiaxes);
+        let t = Transform::new(Vector::new(position), &Vector::unit(e.dim(), 0)).unwrap();
+        let r = Ray::new(Vector::new(point), Vector::new(direction));
+        assert_eq_option_f32(e.intersection_distance(&t, &r), expected_distance);
+    }
+
+    #[test]
+    fn test_moved() {
+        test(
+            1.0, 1.0, 1.0,
+            5.0, 0.0, 0.0,
+            0.0, 0.0, 0.0,
+            1.0, 0.0, 0.0,
+            Some(4.0),
+        )
+    }
+
+    #[test]
+    fn test2d() {
+        test_nd(
+            vec![2.0, 1.0],
+            vec![0.0, 0.0],
+            vec![-5.0, 0.0],
+            vec![1.0, 0.0],
+            Some(3.0),
+        )
+    }
+
+    #[test]
+    fn test2d_miss() {
+        test_nd(
+            vec![2.0, 1.0],
+            vec![0.0, 0.0],
+            vec![-5.0, 1.5],
+            vec![1.0, 0.0],
+            None,
+        )
+    }
+
+    #[test]
+    fn test4d() {
+        test_nd(
+            vec![1.0, 1.0, 1.0, 3.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+            vec![0.0, 0.0, 0.0, -5.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+            Some(3.0),
+        )
+    }
+
+    #[test]
+    fn test5d() {
+        test_nd(
+            vec![1.0, 1.0, 1.0, 1.0, 1.0],
+            vec![0.0, 0.0, 0.0, 0.0, 0.0],
+            vec![-3.0, 0.0, 0.0, 0.0, 0.0],
+            vec![1.0, 0.0, 0.0, 0.0, 0.0],
+            Some(2.0),
+        )
+    }
+
+    #[test]
+    fn test5d_from_inside() {
+        test_nd(
+            vec![1.0, 2.0, 3.0, 4.0, 5.0],
+            vec![1.0, 1.0, 1.0, 1.0, 1.0],
+            vec![1.0, 1.0, 1.0, 1.0, 1.0],
+            vec![0.0, 0.0, 0.0, 0.0, 1.0],
+            Some(5.0),
+        )
+    }
+
+    #[test]
+    fn test_different_dimensions() {
+        test_nd(
+            vec![1.0, 1.0, 1.0, 1.0],
+            vec![0.0, 0.0, 0.0, 0.0],
+            vec![-3.0, 0.0, 0.0],
+            vec![1.0, 0.0, 0.0],
+            None,
+        )
+    }
+
+    #[test]
+    fn intervals_from_inside() {
+        let e = Hyperellipsoid::new(vec![2.0, 2.0]);
+        let r = Ray::new(Vector::from_xy(1.0, 0.0), Vector::from_xy(1.0, 0.0));
+        let intervals = e.intersection_intervals(&Transform::default_nd(2).unwrap(), &r);
+        assert_eq!(intervals.len(), 1);
+        assert_eq_f32(intervals[0].enter, -3.0);
+        assert_eq_f32(intervals[0].exit, 1.0);
+    }
+
+    #[test]
+    fn normal() {
+        let e = Hyperellipsoid::new3d(2.0, 1.0, 1.0);
+        let t = Transform::new_from_coords(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        let n = e.normal(&t, &Vector::from_xyz(3.0, 0.0, 0.0)).unwrap();
+        assert!(n.approximately_equal(&Vector::from_xyz(1.0, 0.0, 0.0), 1e-6));
+        let n = e.normal(&t, &Vector::from_xyz(1.0, 0.0, -1.0)).unwrap();
+        assert!(n.approximately_equal(&Vector::from_xyz(0.0, 0.0, -1.0), 1e-6));
+    }
+
+    #[test]
+    fn spherical_uv() {
+        let e = Hyperellipsoid::new3d(2.0, 1.0, 1.0);
+        let t = Transform::new_from_coords(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        let uv = |x, y, z| e.uv(&t, &Vector::from_xyz(x, y, z)).unwrap();
+        let (u, v) = uv(3.0, 0.0, 0.0);
+        assert_eq_f32(u, 0.5);
+        assert_eq_f32(v, 0.5);
+        let (u, v) = uv(1.0, 1.0, 0.0);
+        assert_eq_f32(u, 0.75);
+        assert_eq_f32(v, 0.5);
+        assert_eq_f32(uv(1.0, 0.0, 1.0).1, 0.0);
+        assert_eq_f32(uv(1.0, 0.0, -1.0).1, 1.0);
+        assert_eq!(e.uv(&t, &Vector::from_xyz(1.0, 0.0, 0.0)), None);
+    }
+}
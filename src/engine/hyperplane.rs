@@ -44,6 +44,45 @@ impl GameObject for Hyperplane {
         let t = (b - normal_dot_ray_point) / normal_dot_ray_direction;
         Some(t)
     }
+
+    /// Inside of hyperplane is the half-space opposite to its normal.
+    fn intersection_intervals(&self, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+        if ray.direction.dim() != transform.get_direction().dim() ||
+            transform.get_direction().dim() != transform.position.dim() {
+            return vec![];
+        }
+
+        let normal = transform.get_direction();
+        let normal_dot_ray_direction = normal.dot_product(&ray.direction);
+        // Ray is parallel to hyperplane: it is either inside or outside for all parameters.
+        if normal_dot_ray_direction == 0.0 {
+            if normal.dot_product(&ray.point) <= normal.dot_product(&transform.position) {
+                return vec![Interval::everything()];
+            }
+            return vec![];
+        }
+
+        let t = self.intersection_distance(transform, ray).unwrap();
+        if normal_dot_ray_direction > 0.0 {
+            vec![Interval::new(f32::NEG_INFINITY, t)]
+        } else {
+            vec![Interval::new(t, f32::INFINITY)]
+        }
+    }
+
+    /// Normal is the same in every point: direction of the transform.
+    fn normal(&self, transform: &Transform, _point: &Vector<f32>) -> Option<Vector<f32>> {
+        Some(transform.get_direction().clone())
+    }
+
+    /// Planar mapping: coordinates of `point` along the first two axes of the hyperplane
+    /// measured from the transform position in units of its scale.
+    fn uv(&self, transform: &Transform, point: &Vector<f32>) -> Option<(f32, f32)> {
+        let point = transform.to_local_point(point)?;
+        let axes = CoordinateSystem::from_direction(Vector::zero(point.dim()), transform.get_direction())?.base;
+        let coordinate = |k: usize| axes.get(k).map_or(0.0, |axis| point.dot_product(axis));
+        Some((coordinate(1), coordinate(2)))
+    }
 }
 
 
@@ -103,4 +142,85 @@ mod hyperplane_tests {
             .expect("Intersection must be, but it's not.");
         assert_eq_f32(actual, expected);
     }
-}
\ No newline at end of file
+
+    fn intersection_nd(position: Vec<f32>, normal: Vec<f32>, ray: Ray, expected: Option<f32>) {
+        let p = Hyperplane::default();
+        let transform = Transform::new(Vector::new(position), &Vector::new(normal)).unwrap();
+        let actual = p.intersection_distance(&transform, &ray);
+        match (actual, expected) {
+            (Some(actual), Some(expected)) => assert_eq_f32(actual, expected),
+            (None, None) => (),
+            _ => panic!("{:?} != {:?}", actual, expected),
+        }
+    }
+
+    #[test]
+    fn intersection2d() {
+        intersection_nd(
+            vec![0.0, 2.0],
+            vec![0.0, 1.0],
+            Ray::new(Vector::from_xy(5.0, -1.0), Vector::from_xy(0.0, 1.0)),
+            Some(3.0),
+        );
+    }
+
+    #[test]
+    fn intersection4d() {
+        intersection_nd(
+            vec![0.0, 0.0, 0.0, 1.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+            Ray::new(Vector::new(vec![1.0, 2.0, 3.0, -1.0]), Vector::new(vec![0.0, 0.0, 0.0, 0.5])),
+            Some(4.0),
+        );
+    }
+
+    #[test]
+    fn intersection4d_parallel() {
+        intersection_nd(
+            vec![0.0, 0.0, 0.0, 1.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+            Ray::new(Vector::new(vec![1.0, 2.0, 3.0, -1.0]), Vector::new(vec![1.0, 0.0, 0.0, 0.0])),
+            None,
+        );
+    }
+
+    #[test]
+    fn intersection5d() {
+        intersection_nd(
+            vec![0.0, 0.0, 0.0, 0.0, 0.0],
+            vec![1.0, 1.0, 1.0, 1.0, 1.0],
+            Ray::new(Vector::new(vec![-1.0, -1.0, -1.0, -1.0, -1.0]), Vector::new(vec![1.0, 0.0, 0.0, 0.0, 0.0])),
+            Some(5.0),
+        );
+    }
+
+    #[test]
+    fn intervals_half_space() {
+        let p = Hyperplane::default();
+        let ray = Ray::new(
+            Vector::from_xyz(-1.0, 0.0, 0.0),
+            Vector::from_xyz(1.0, 0.0, 0.0),
+        );
+        let intervals = p.intersection_intervals(&Transform::default(), &ray);
+        assert_eq!(intervals, vec![Interval::new(f32::NEG_INFINITY, 1.0)]);
+    }
+
+    #[test]
+    fn planar_uv() {
+        let p = Hyperplane;
+        let mut transform = Transform::new_from_coords(1.0, 2.0, 3.0, 1.0, 0.0, 0.0).unwrap();
+        let (u, v) = p.uv(&transform, &Vector::from_xyz(1.0, 2.5, 1.0)).unwrap();
+        assert_eq_f32(u, 0.5);
+        assert_eq_f32(v, -2.0);
+
+        transform.scale = 2.0;
+        let (u, v) = p.uv(&transform, &Vector::from_xyz(1.0, 2.5, 1.0)).unwrap();
+        assert_eq_f32(u, 0.25);
+        assert_eq_f32(v, -1.0);
+
+        let transform = Transform::new(Vector::zero(2), &Vector::from_xy(0.0, 1.0)).unwrap();
+        let (u, v) = p.uv(&transform, &Vector::from_xy(3.0, 0.0)).unwrap();
+        assert_eq_f32(u, 3.0);
+        assert_eq_f32(v, 0.0);
+    }
+}
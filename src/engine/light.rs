@@ -0,0 +1,179 @@
+use super::*;
+use crate::math::*;
+
+
+/// Kind of light source and its placement in the world.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LightKind {
+    /// Light which comes equally from everywhere, it lights surfaces without shading.
+    Ambient,
+    /// Light emitted from a point in all directions, it fades with the square of distance.
+    Point { position: Vector<f32> },
+    /// Light which comes from infinity along `direction`, like sunlight.
+    Directional { direction: Vector<f32> },
+    /// Point light which shines only inside of the cone around `direction`.
+    /// `angle` is the angle between the axis of the cone and its side.
+    Spot { position: Vector<f32>, direction: Vector<f32>, angle: f32 },
+}
+
+/// Light coming to a surface point from a light source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightSample {
+    /// Unit vector from the surface point to the light.
+    pub direction: Vector<f32>,
+    /// Distance to the light, infinite for directional lights.
+    pub distance: f32,
+    /// Colour and strength of light at the surface point.
+    pub radiance: Rgb,
+}
+
+/// Light source of the scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Rgb,
+    pub intensity: f32,
+    /// Radius of area light. Lights with positive radius cast soft shadows.
+    /// For directional lights it is the angular radius of the source in radians, like of the sun.
+    pub radius: f32,
+}
+
+/// Part of the spot cone angle where light fades out to the side of the cone.
+const SPOT_EDGE: f32 = 0.2;
+
+impl Light {
+    pub fn ambient(color: Rgb, intensity: f32) -> Light {
+        Light { kind: LightKind::Ambient, color, intensity, radius: 0.0 }
+    }
+
+    pub fn point(position: Vector<f32>, color: Rgb, intensity: f32) -> Light {
+        Light { kind: LightKind::Point { position }, color, intensity, radius: 0.0 }
+    }
+
+    /// Creates directional light shining along `direction`.
+    /// If `direction` is zero returns `None`.
+    pub fn directional(direction: &Vector<f32>, color: Rgb, intensity: f32) -> Option<Light> {
+        let mut direction = direction.clone();
+        if !direction.normalize() {
+            return None;
+        }
+        Some(Light { kind: LightKind::Directional { direction }, color, intensity, radius: 0.0 })
+    }
+
+    /// Creates spot light at `position` shining along `direction` inside of the cone with half-angle `angle`.
+    /// If `direction` is zero or dimensions of `position` and `direction` differ returns `None`.
+    pub fn spot(position: Vector<f32>, direction: &Vector<f32>, angle: f32, color: Rgb, intensity: f32) -> Option<Light> {
+        let mut direction = direction.clone();
+        if position.dim() != direction.dim() || !direction.normalize() {
+            return None;
+        }
+        Some(Light { kind: LightKind::Spot { position, direction, angle }, color, intensity, radius: 0.0 })
+    }
+
+    /// Returns position of point and spot lights.
+    pub fn position(&self) -> Option<&Vector<f32>> {
+        match &self.kind {
+            LightKind::Point { position } | LightKind::Spot { position, .. } => Some(position),
+            _ => None,
+        }
+    }
+
+    /// Returns light which lights every surface regardless of its orientation.
+    pub fn ambient_radiance(&self) -> Rgb {
+        match self.kind {
+            LightKind::Ambient => self.color * self.intensity,
+            _ => Rgb::BLACK,
+        }
+    }
+
+    /// Returns light coming to `point` from the light source.
+    /// Returns `None` for ambient light, for points out of spot cone, for points
+    /// in the position of the light and if dimensions of the light and `point` differ.
+    pub fn sample(&self, point: &Vector<f32>) -> Option<LightSample> {
+        let radiance = self.color * self.intensity;
+        match &self.kind {
+            LightKind::Ambient => None,
+            LightKind::Directional { direction } => {
+                if direction.dim() != point.dim() {
+                    return None;
+                }
+                Some(LightSample { direction: -direction.clone(), distance: f32::INFINITY, radiance })
+            }
+            LightKind::Point { position } => {
+                let (direction, distance) = Light::direction_to(position, point)?;
+                Some(LightSample { direction, distance, radiance: radiance * (1.0 / (distance * distance)) })
+            }
+            LightKind::Spot { position, direction: axis, angle } => {
+                let (direction, distance) = Light::direction_to(position, point)?;
+                let cosine = -direction.dot_product(axis);
+                let (outer, inner) = (angle.cos(), (angle * (1.0 - SPOT_EDGE)).cos());
+                if cosine <= outer {
+                    return None;
+                }
+                let cone = if cosine >= inner { 1.0 } else { (cosine - outer) / (inner - outer) };
+                Some(LightSample { direction, distance, radiance: radiance * (cone / (distance * distance)) })
+            }
+        }
+    }
+
+    /// Returns unit vector from `point` to `position` and distance between them.
+    fn direction_to(position: &Vector<f32>, point: &Vector<f32>) -> Option<(Vector<f32>, f32)> {
+        let direction = (position - point)?;
+        let distance = direction.magnitude();
+        if distance == 0.0 {
+            return None;
+        }
+        Some((&direction / distance, distance))
+    }
+}
+
+
+#[cfg(test)]
+mod light_tests {
+    use std::f32::consts::PI;
+
+    use crate::utils::assert_eq_f32;
+
+    use super::*;
+
+    #[test]
+    fn point_light_falloff() {
+        let light = Light::point(Vector::from_xyz(0.0, 0.0, 2.0), Rgb::WHITE, 8.0);
+        let sample = light.sample(&Vector::zero3()).unwrap();
+        assert_eq!(sample.direction, Vector::from_xyz(0.0, 0.0, 1.0));
+        assert_eq_f32(sample.distance, 2.0);
+        assert_eq!(sample.radiance, Rgb::grey(2.0));
+        assert!(light.sample(&Vector::from_xyz(0.0, 0.0, 2.0)).is_none());
+    }
+
+    #[test]
+    fn directional_light() {
+        let light = Light::directional(&Vector::from_xyz(0.0, 0.0, -3.0), Rgb::WHITE, 0.5).unwrap();
+        let sample = light.sample(&Vector::from_xyz(10.0, 5.0, 0.0)).unwrap();
+        assert_eq!(sample.direction, Vector::from_xyz(0.0, 0.0, 1.0));
+        assert_eq!(sample.distance, f32::INFINITY);
+        assert_eq!(sample.radiance, Rgb::grey(0.5));
+        assert!(Light::directional(&Vector::zero3(), Rgb::WHITE, 1.0).is_none());
+    }
+
+    #[test]
+    fn spot_light_cone() {
+        let light = Light::spot(
+            Vector::zero3(), &Vector::from_xyz(1.0, 0.0, 0.0), PI / 4.0, Rgb::WHITE, 1.0
+        ).unwrap();
+        assert_eq!(light.sample(&Vector::from_xyz(1.0, 0.0, 0.0)).unwrap().radiance, Rgb::WHITE);
+        assert!(light.sample(&Vector::from_xyz(1.0, 1.1, 0.0)).is_none());
+        assert!(light.sample(&Vector::from_xyz(-1.0, 0.0, 0.0)).is_none());
+        // Near the side of the cone light fades out.
+        let edge = light.sample(&Vector::from_xyz(1.0, 0.95, 0.0)).unwrap().radiance;
+        assert!(0.0 < edge.r && edge.r < 0.5);
+    }
+
+    #[test]
+    fn ambient_light() {
+        let light = Light::ambient(Rgb::new(1.0, 0.5, 0.0), 0.5);
+        assert_eq!(light.ambient_radiance(), Rgb::new(0.5, 0.25, 0.0));
+        assert!(light.sample(&Vector::zero3()).is_none());
+        assert_eq!(Light::point(Vector::zero3(), Rgb::WHITE, 1.0).ambient_radiance(), Rgb::BLACK);
+    }
+}
@@ -0,0 +1,56 @@
+use super::*;
+
+
+/// Appearance of a game object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    /// Colour of the surface lit by white light.
+    pub color: Rgb,
+    /// Pattern which replaces `color` on objects which know texture coordinates of their surface.
+    pub texture: Option<Texture>,
+    /// Character to draw the object with. If `None` renderer picks a character itself.
+    pub glyph: Option<char>,
+    /// Light emitted by the surface itself, it is visible without any light source.
+    pub emissive: Rgb,
+    /// Strength of specular highlights, 0 for matte surfaces.
+    pub specular: f32,
+    /// Exponent of Phong specular term, the greater it is the smaller highlights are.
+    pub shininess: f32,
+    /// Part of light which is reflected like in a mirror, in `[0, 1]`.
+    pub reflectivity: f32,
+    /// Part of light which passes through the surface, in `[0, 1]`.
+    pub transparency: f32,
+    /// Index of refraction of transparent material, e.g. 1.33 for water and 1.5 for glass.
+    pub refractive_index: f32,
+}
+
+impl Material {
+    /// Creates opaque matte material of the given colour.
+    pub fn from_color(color: Rgb) -> Material {
+        Material { color, ..Material::default() }
+    }
+
+    /// Returns colour of the surface at texture coordinates `uv`.
+    pub fn color_at(&self, uv: Option<(f32, f32)>) -> Rgb {
+        match (&self.texture, uv) {
+            (Some(texture), Some(uv)) => texture.sample(uv),
+            _ => self.color,
+        }
+    }
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material {
+            color: Rgb::grey(0.8),
+            texture: None,
+            glyph: None,
+            emissive: Rgb::BLACK,
+            specular: 0.0,
+            shininess: 16.0,
+            reflectivity: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.5,
+        }
+    }
+}
@@ -1,21 +1,63 @@
+pub mod bvh;
 pub mod camera;
 pub mod canvas;
+pub mod csg;
 pub mod entity;
 pub mod event_system;
+pub mod fog;
+pub mod frustum;
 pub mod game_config;
 pub mod game_object;
 pub mod game;
+pub mod glyph;
+pub mod heightmap;
 pub mod hyperplane;
 pub mod hyperellipsoid;
+pub mod light;
+pub mod material;
+pub mod palette;
+pub mod post_process;
+pub mod projection;
+pub mod quadric;
+pub mod render_mode;
+pub mod rgb;
+pub mod sdf;
+pub mod spatial_hash;
+pub mod texture;
+pub mod tile_canvas;
+pub mod tile_map;
 pub mod transform;
+pub mod viewport;
+pub mod voxel_grid;
 
+pub use bvh::*;
 pub use camera::*;
 pub use canvas::*;
+pub use csg::*;
 pub use entity::*;
 pub use event_system::*;
+pub use fog::*;
+pub use frustum::*;
 pub use game_config::*;
 pub use game_object::*;
 pub use game::*;
+pub use glyph::*;
+pub use heightmap::*;
 pub use hyperplane::*;
 pub use hyperellipsoid::*;
-pub use transform::*;
\ No newline at end of file
+pub use light::*;
+pub use material::*;
+pub use palette::*;
+pub use post_process::*;
+pub use projection::*;
+pub use quadric::*;
+pub use render_mode::*;
+pub use rgb::*;
+pub use sdf::*;
+pub use spatial_hash::*;
+pub use texture::*;
+pub use tile_canvas::*;
+pub use tile_map::*;
+pub use transform::*;
+pub use viewport::*;
+pub use voxel_grid::*;
@@ -0,0 +1,290 @@
+use std::fmt;
+use std::str::FromStr;
+
+use console_engine::Color;
+
+use super::*;
+use crate::math::*;
+
+
+/// Colours of 16-colour terminals with their usual RGB values.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+/// Channel levels of 6×6×6 colour cube of xterm-256 palette, it starts at index 16.
+const XTERM_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+/// Grey ramp of xterm-256 palette, indices from 232 to 255 are greys `8 + 10 * k`.
+const XTERM_GREYS: u8 = 24;
+/// Bayer matrix of ordered dithering with thresholds from 0 to 15.
+const BAYER4: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Set of colours which terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// 16 named colours, which look differently in different terminal themes.
+    Ansi16,
+    /// 256 colours of xterm: colour cube and grey ramp.
+    Xterm256,
+    /// 24-bit colours.
+    #[default]
+    TrueColor,
+}
+
+impl ColorMode {
+    /// Detects colours supported by terminal from `COLORTERM` and `TERM` environment variables.
+    pub fn detect() -> ColorMode {
+        let colorterm = std::env::var("COLORTERM").ok();
+        let term = std::env::var("TERM").ok();
+        ColorMode::from_terminal(colorterm.as_deref(), term.as_deref())
+    }
+
+    fn from_terminal(colorterm: Option<&str>, term: Option<&str>) -> ColorMode {
+        if matches!(colorterm, Some("truecolor") | Some("24bit")) {
+            ColorMode::TrueColor
+        } else if term.is_some_and(|term| term.contains("256color")) {
+            ColorMode::Xterm256
+        } else {
+            ColorMode::Ansi16
+        }
+    }
+
+    /// Returns the nearest colour of terminal to `color` and its RGB value.
+    pub fn quantize(&self, color: Rgb) -> (Color, Rgb) {
+        let (r, g, b) = color.to_u8();
+        let distance = |(r2, g2, b2): (u8, u8, u8)| {
+            let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2);
+            d(r, r2) + d(g, g2) + d(b, b2)
+        };
+        let (console_color, (r, g, b)) = match self {
+            ColorMode::TrueColor => (Color::Rgb { r, g, b }, (r, g, b)),
+            ColorMode::Ansi16 => *ANSI16.iter().min_by_key(|(_, rgb)| distance(*rgb)).unwrap(),
+            ColorMode::Xterm256 => {
+                let level = |x: u8| (0..6).min_by_key(|k| (XTERM_CUBE_LEVELS[*k] as i32 - x as i32).abs()).unwrap();
+                let (lr, lg, lb) = (level(r), level(g), level(b));
+                let cube = (
+                    Color::AnsiValue((16 + 36 * lr + 6 * lg + lb) as u8),
+                    (XTERM_CUBE_LEVELS[lr], XTERM_CUBE_LEVELS[lg], XTERM_CUBE_LEVELS[lb]),
+                );
+                let grey_level = |k: u8| 8 + 10 * k;
+                let k = (0..XTERM_GREYS)
+                    .min_by_key(|k| distance((grey_level(*k), grey_level(*k), grey_level(*k))))
+                    .unwrap();
+                let grey = (Color::AnsiValue(232 + k), (grey_level(k), grey_level(k), grey_level(k)));
+                if distance(grey.1) < distance(cube.1) { grey } else { cube }
+            }
+        };
+        (console_color, Rgb::from_u8(r, g, b))
+    }
+
+    /// Returns typical distance between neighbour levels of channel, it is the amplitude of ordered dithering.
+    fn step(&self) -> f32 {
+        match self {
+            ColorMode::Ansi16 => 0.5,
+            ColorMode::Xterm256 => 0.16,
+            ColorMode::TrueColor => 1.0 / 255.0,
+        }
+    }
+}
+
+impl fmt::Display for ColorMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ColorMode::Ansi16 => "ansi16",
+            ColorMode::Xterm256 => "xterm256",
+            ColorMode::TrueColor => "truecolor",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColorMode, String> {
+        match s {
+            "ansi16" => Ok(ColorMode::Ansi16),
+            "xterm256" => Ok(ColorMode::Xterm256),
+            "truecolor" => Ok(ColorMode::TrueColor),
+            _ => Err(format!("Unknown colour mode `{}`", s)),
+        }
+    }
+}
+
+/// How colours between colours of palette are imitated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dithering {
+    /// Every colour is replaced by the nearest one.
+    #[default]
+    None,
+    /// Colours are shifted by thresholds of 4×4 Bayer matrix before rounding, it makes regular patterns.
+    Ordered,
+    /// Floyd–Steinberg error diffusion: error of rounding is passed to the next pixels.
+    ErrorDiffusion,
+}
+
+impl fmt::Display for Dithering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Dithering::None => "none",
+            Dithering::Ordered => "ordered",
+            Dithering::ErrorDiffusion => "error_diffusion",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Dithering {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Dithering, String> {
+        match s {
+            "none" => Ok(Dithering::None),
+            "ordered" => Ok(Dithering::Ordered),
+            "error_diffusion" => Ok(Dithering::ErrorDiffusion),
+            _ => Err(format!("Unknown dithering `{}`", s)),
+        }
+    }
+}
+
+/// Converts colours of the world to colours of terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Palette {
+    pub mode: ColorMode,
+    pub dithering: Dithering,
+}
+
+impl Palette {
+    pub fn new(mode: ColorMode, dithering: Dithering) -> Palette {
+        Palette { mode, dithering }
+    }
+
+    pub fn new_from_game_config(config: &GameConfig) -> Palette {
+        Palette::new(config.color_mode, config.dithering)
+    }
+
+    /// Returns the nearest colour of terminal to `color` without dithering.
+    pub fn console_color(&self, color: Rgb) -> Color {
+        self.mode.quantize(color).0
+    }
+
+    /// Returns the nearest colour of terminal to RGB `color`, other colours are kept.
+    pub fn adapt(&self, color: Color) -> Color {
+        match color {
+            Color::Rgb { r, g, b } => self.console_color(Rgb::from_u8(r, g, b)),
+            _ => color,
+        }
+    }
+
+    /// Converts image of `colors` to colours of terminal with dithering.
+    /// `None` pixels stay `None` and do not take error of error diffusion.
+    pub fn quantize(&self, colors: &Matrix<Option<Rgb>>) -> Matrix<Option<Color>> {
+        let (height, width) = (colors.rows_count(), colors.cols_count());
+        match self.dithering {
+            Dithering::None => Matrix::from_rule(height, width, |i, j| colors[i][j].map(|color| self.console_color(color))),
+            Dithering::Ordered => Matrix::from_rule(
+                height, width,
+                |i, j| colors[i][j].map(|color| {
+                    let threshold = (BAYER4[i % 4][j % 4] as f32 + 0.5) / 16.0 - 0.5;
+                    self.console_color(color + Rgb::grey(threshold * self.mode.step()))
+                })
+            ),
+            Dithering::ErrorDiffusion => {
+                let mut colors = colors.clone();
+                let mut result = Matrix::from_rule(height, width, |_, _| None);
+                for i in 0..height {
+                    for j in 0..width {
+                        let color = match colors[i][j] {
+                            None => continue,
+                            Some(color) => color,
+                        };
+                        let (console_color, quantized) = self.mode.quantize(color);
+                        result[i][j] = Some(console_color);
+                        let error = color - quantized;
+                        let neighbours = [(i, j + 1, 7.0), (i + 1, j.wrapping_sub(1), 3.0), (i + 1, j, 5.0), (i + 1, j + 1, 1.0)];
+                        for (k, l, weight) in neighbours {
+                            if k < height && l < width {
+                                if let Some(neighbour) = &mut colors[k][l] {
+                                    *neighbour += error * (weight / 16.0);
+                                }
+                            }
+                        }
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod palette_tests {
+    use super::*;
+
+    #[test]
+    fn quantize_colors() {
+        let orange = Rgb::from_u8(250, 130, 10);
+        assert_eq!(ColorMode::TrueColor.quantize(orange).0, Color::Rgb { r: 250, g: 130, b: 10 });
+        assert_eq!(ColorMode::Ansi16.quantize(Rgb::from_u8(200, 20, 30)).0, Color::Red);
+        assert_eq!(ColorMode::Ansi16.quantize(Rgb::grey(0.3)).0, Color::DarkGrey);
+        // Cube colour (5, 2, 0).
+        assert_eq!(ColorMode::Xterm256.quantize(orange), (Color::AnsiValue(208), Rgb::from_u8(255, 135, 0)));
+        // Grey ramp is finer than cube diagonal.
+        assert_eq!(ColorMode::Xterm256.quantize(Rgb::from_u8(50, 50, 50)).0, Color::AnsiValue(236));
+        let palette = Palette::new(ColorMode::Ansi16, Dithering::None);
+        assert_eq!(palette.adapt(Color::Rgb { r: 0, g: 0, b: 250 }), Color::Blue);
+        assert_eq!(palette.adapt(Color::DarkCyan), Color::DarkCyan);
+    }
+
+    #[test]
+    fn detect() {
+        assert_eq!(ColorMode::from_terminal(Some("truecolor"), Some("xterm")), ColorMode::TrueColor);
+        assert_eq!(ColorMode::from_terminal(Some("24bit"), None), ColorMode::TrueColor);
+        assert_eq!(ColorMode::from_terminal(None, Some("xterm-256color")), ColorMode::Xterm256);
+        assert_eq!(ColorMode::from_terminal(None, Some("linux")), ColorMode::Ansi16);
+        assert_eq!(ColorMode::from_terminal(None, None), ColorMode::Ansi16);
+    }
+
+    #[test]
+    fn dithering() {
+        // Grey a bit darker than the middle between black and dark grey of 16 colours.
+        let grey = Rgb::grey(0.24);
+        let colors = Matrix::from_rule(4, 4, |i, _| if i == 3 { None } else { Some(grey) });
+        let count = |palette: Palette| {
+            let result = palette.quantize(&colors);
+            assert!((0..4).all(|j| result[3][j].is_none()));
+            (0..12).filter(|k| result[k / 4][k % 4] == Some(Color::DarkGrey)).count()
+        };
+        assert_eq!(count(Palette::new(ColorMode::Ansi16, Dithering::None)), 0);
+        // About half of pixels become dark grey.
+        assert!((4..=8).contains(&count(Palette::new(ColorMode::Ansi16, Dithering::Ordered))));
+        assert!((4..=8).contains(&count(Palette::new(ColorMode::Ansi16, Dithering::ErrorDiffusion))));
+    }
+
+    #[test]
+    fn parse() {
+        for mode in [ColorMode::Ansi16, ColorMode::Xterm256, ColorMode::TrueColor] {
+            assert_eq!(mode.to_string().parse::<ColorMode>(), Ok(mode));
+        }
+        for dithering in [Dithering::None, Dithering::Ordered, Dithering::ErrorDiffusion] {
+            assert_eq!(dithering.to_string().parse::<Dithering>(), Ok(dithering));
+        }
+        assert!("256".parse::<ColorMode>().is_err());
+        assert!("bayer".parse::<Dithering>().is_err());
+    }
+}
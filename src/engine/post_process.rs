@@ -0,0 +1,243 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::*;
+use crate::math::*;
+
+/// Relative change of inverse depth per pixel found by Sobel operator which makes an outline.
+const OUTLINE_DEPTH_RATIO: f32 = 0.2;
+/// Distance in pixels to neighbours which ambient occlusion compares depth with.
+const AMBIENT_OCCLUSION_RADIUS: usize = 2;
+/// Relative concavity of depth between a pixel and its pair of neighbours which makes it occluded.
+const AMBIENT_OCCLUSION_DEPTH_RATIO: f32 = 0.02;
+/// Neighbours farther from the pixel than this part of its depth do not occlude it,
+/// so objects far in front do not cast dark halos on the background.
+const AMBIENT_OCCLUSION_RANGE: f32 = 0.5;
+
+/// Pass which changes colours of `Canvas` after shading using its depth buffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostProcess {
+    /// Pixels where depth changes sharply, found by Sobel operator, are painted black.
+    Outline,
+    /// Screen-space ambient occlusion: pixels in concave creases of depth buffer are darkened
+    /// by up to `strength`.
+    AmbientOcclusion { strength: f32 },
+    /// Colours fade to black towards corners of the screen by up to `strength`.
+    Vignette { strength: f32 },
+    /// Extended Reinhard operator which maps brightness from `[0, white]` into `[0, 1]`.
+    ToneMapping { white: f32 },
+    /// Channels are raised to power `1 / gamma`.
+    Gamma { gamma: f32 },
+    /// Every second row is darkened by `strength` like on CRT screen.
+    Scanlines { strength: f32 },
+}
+
+impl fmt::Display for PostProcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostProcess::Outline => write!(f, "outline"),
+            PostProcess::AmbientOcclusion { strength } => write!(f, "ambient_occlusion {}", strength),
+            PostProcess::Vignette { strength } => write!(f, "vignette {}", strength),
+            PostProcess::ToneMapping { white } => write!(f, "tone_mapping {}", white),
+            PostProcess::Gamma { gamma } => write!(f, "gamma {}", gamma),
+            PostProcess::Scanlines { strength } => write!(f, "scanlines {}", strength),
+        }
+    }
+}
+
+impl FromStr for PostProcess {
+    type Err = String;
+
+    /// Parses `outline`, `ambient_occlusion <strength>`, `vignette <strength>`,
+    /// `tone_mapping <white>`, `gamma <gamma>` or `scanlines <strength>`.
+    fn from_str(s: &str) -> Result<PostProcess, String> {
+        let mut words = s.split_whitespace();
+        let (name, parameter) = (words.next(), words.next());
+        if words.next().is_some() {
+            return Err(format!("Unknown post-process `{}`", s));
+        }
+        let value = || parameter
+            .and_then(|parameter| parameter.parse::<f32>().ok())
+            .filter(|value| value.is_finite())
+            .ok_or(format!("Invalid parameter of post-process `{}`", s));
+        let out_of_range = || format!("Parameter of post-process `{}` is out of range", s);
+        let strength = || value().and_then(|value| if (0.0..=1.0).contains(&value) { Ok(value) } else { Err(out_of_range()) });
+        let positive = || value().and_then(|value| if value > 0.0 { Ok(value) } else { Err(out_of_range()) });
+        match (name, parameter) {
+            (Some("outline"), None) => Ok(PostProcess::Outline),
+            (Some("ambient_occlusion"), _) => Ok(PostProcess::AmbientOcclusion { strength: strength()? }),
+            (Some("vignette"), _) => Ok(PostProcess::Vignette { strength: strength()? }),
+            (Some("tone_mapping"), _) => Ok(PostProcess::ToneMapping { white: positive()? }),
+            (Some("gamma"), _) => Ok(PostProcess::Gamma { gamma: positive()? }),
+            (Some("scanlines"), _) => Ok(PostProcess::Scanlines { strength: strength()? }),
+            _ => Err(format!("Unknown post-process `{}`", s)),
+        }
+    }
+}
+
+impl PostProcess {
+    /// Returns `colors` changed by the pass, `distances` is the depth buffer of the same size.
+    /// Misses stay misses.
+    pub fn apply(&self, distances: &Matrix<Option<f32>>, colors: &Matrix<Option<Rgb>>) -> Matrix<Option<Rgb>> {
+        let (height, width) = (colors.rows_count(), colors.cols_count());
+        let factor = |i: usize, j: usize| match *self {
+            PostProcess::Outline => if is_outline(distances, i, j) { 0.0 } else { 1.0 },
+            PostProcess::AmbientOcclusion { strength } => 1.0 - strength * occlusion(distances, i, j),
+            PostProcess::Vignette { strength } => {
+                let x = 2.0 * (j as f32 + 0.5) / width as f32 - 1.0;
+                let y = 2.0 * (i as f32 + 0.5) / height as f32 - 1.0;
+                (1.0 - strength * 0.5 * (x * x + y * y)).max(0.0)
+            }
+            PostProcess::Scanlines { strength } => if i % 2 == 1 { 1.0 - strength } else { 1.0 },
+            PostProcess::ToneMapping { .. } | PostProcess::Gamma { .. } => 1.0,
+        };
+        let channel = |c: f32| match *self {
+            PostProcess::ToneMapping { white } => {
+                let c = c.max(0.0);
+                c * (1.0 + c / (white * white)) / (1.0 + c)
+            }
+            PostProcess::Gamma { gamma } => c.max(0.0).powf(1.0 / gamma),
+            _ => c,
+        };
+        Matrix::from_rule(height, width, |i, j| {
+            let color = colors[i][j]? * factor(i, j);
+            Some(Rgb::new(channel(color.r), channel(color.g), channel(color.b)))
+        })
+    }
+}
+
+/// Returns inverse depth of pixel clamped to the image, 0 for misses which are infinitely far.
+fn inverse_depth(distances: &Matrix<Option<f32>>, i: isize, j: isize) -> f32 {
+    let i = i.clamp(0, distances.rows_count() as isize - 1) as usize;
+    let j = j.clamp(0, distances.cols_count() as isize - 1) as usize;
+    distances[i][j].map_or(0.0, |distance| 1.0 / distance)
+}
+
+/// Returns true if Sobel gradient of inverse depth at hit pixel is large relative to its inverse depth.
+/// Inverse depth changes linearly across flat surfaces, so only their strongly tilted parts are outlined.
+fn is_outline(distances: &Matrix<Option<f32>>, i: usize, j: usize) -> bool {
+    let distance = match distances[i][j] {
+        Some(distance) => distance,
+        None => return false,
+    };
+    let (i, j) = (i as isize, j as isize);
+    let d = |di: isize, dj: isize| inverse_depth(distances, i + di, j + dj);
+    let gx = d(-1, 1) + 2.0 * d(0, 1) + d(1, 1) - d(-1, -1) - 2.0 * d(0, -1) - d(1, -1);
+    let gy = d(1, -1) + 2.0 * d(1, 0) + d(1, 1) - d(-1, -1) - 2.0 * d(-1, 0) - d(-1, 1);
+    // Sobel kernel sums differences over 2 pixels with total weight 4.
+    (gx * gx + gy * gy).sqrt() / 8.0 * distance > OUTLINE_DEPTH_RATIO
+}
+
+/// Returns part of pairs of opposite neighbours around pixel which make a concave crease with it.
+/// Inverse depth of pixel on flat surface is the mean of inverse depths of its opposite neighbours,
+/// in concave crease it is smaller.
+fn occlusion(distances: &Matrix<Option<f32>>, i: usize, j: usize) -> f32 {
+    let distance = match distances[i][j] {
+        Some(distance) => distance,
+        None => return 0.0,
+    };
+    let (height, width) = (distances.rows_count() as isize, distances.cols_count() as isize);
+    let neighbour = |di: isize, dj: isize| {
+        let (k, l) = (i as isize + di, j as isize + dj);
+        if k < 0 || l < 0 || k >= height || l >= width {
+            return None;
+        }
+        distances[k as usize][l as usize].filter(|other| (other - distance).abs() < AMBIENT_OCCLUSION_RANGE * distance)
+    };
+    let directions = [(0, 1), (1, 0), (1, 1), (1, -1)];
+    let mut occluded = 0;
+    for radius in 1..=AMBIENT_OCCLUSION_RADIUS as isize {
+        for (di, dj) in directions {
+            if let (Some(a), Some(b)) = (neighbour(di * radius, dj * radius), neighbour(-di * radius, -dj * radius)) {
+                if (1.0 / a + 1.0 / b - 2.0 / distance) * distance > AMBIENT_OCCLUSION_DEPTH_RATIO {
+                    occluded += 1;
+                }
+            }
+        }
+    }
+    occluded as f32 / (directions.len() * AMBIENT_OCCLUSION_RADIUS) as f32
+}
+
+
+#[cfg(test)]
+mod post_process_tests {
+    use super::*;
+
+    fn flat(height: usize, width: usize, distance: f32) -> Matrix<Option<f32>> {
+        Matrix::from_rule(height, width, |_, _| Some(distance))
+    }
+
+    fn grey(height: usize, width: usize, value: f32) -> Matrix<Option<Rgb>> {
+        Matrix::from_rule(height, width, |_, _| Some(Rgb::grey(value)))
+    }
+
+    #[test]
+    fn color_passes() {
+        let distances = flat(4, 4, 1.0);
+        let colors = grey(4, 4, 1.0);
+        let vignette = PostProcess::Vignette { strength: 0.5 }.apply(&distances, &colors);
+        assert!(vignette[1][1].unwrap().r > vignette[0][0].unwrap().r);
+        assert_eq!(vignette[0][0], vignette[3][3]);
+
+        let scanlines = PostProcess::Scanlines { strength: 0.25 }.apply(&distances, &colors);
+        assert_eq!((scanlines[0][2], scanlines[1][2]), (Some(Rgb::WHITE), Some(Rgb::grey(0.75))));
+
+        let tone_mapped = PostProcess::ToneMapping { white: 1.0 }.apply(&distances, &colors);
+        assert_eq!(tone_mapped[2][2], Some(Rgb::WHITE));
+        let tone_mapped = PostProcess::ToneMapping { white: 3.0 }.apply(&distances, &grey(4, 4, 1.0));
+        assert!(tone_mapped[2][2].unwrap().r < 1.0);
+
+        let gamma = PostProcess::Gamma { gamma: 2.0 }.apply(&distances, &grey(4, 4, 0.25));
+        assert_eq!(gamma[0][0], Some(Rgb::grey(0.5)));
+
+        let mut colors = colors;
+        colors[0][0] = None;
+        assert_eq!(PostProcess::Gamma { gamma: 2.0 }.apply(&distances, &colors)[0][0], None);
+    }
+
+    #[test]
+    fn depth_passes() {
+        // Floor tilted away from the camera: inverse depth changes linearly by rows.
+        let floor = Matrix::from_rule(7, 7, |i, _| Some(1.0 / (1.0 - 0.05 * i as f32)));
+        let colors = grey(7, 7, 1.0);
+        for pass in [PostProcess::Outline, PostProcess::AmbientOcclusion { strength: 1.0 }] {
+            assert_eq!(pass.apply(&floor, &colors), colors);
+        }
+
+        // Box at distance 2 in front of wall at distance 4.
+        let mut scene = Matrix::from_rule(7, 7, |i, j| Some(if (2..5).contains(&i) && (2..5).contains(&j) { 2.0 } else { 4.0 }));
+        scene[0][0] = None;
+        let outline = PostProcess::Outline.apply(&scene, &colors);
+        assert_eq!(outline[2][2], Some(Rgb::BLACK));
+        assert_eq!(outline[3][3], Some(Rgb::WHITE));
+        assert_eq!(outline[6][6], Some(Rgb::WHITE));
+        // Misses are infinitely far, so surfaces next to them are outlined.
+        assert_eq!(outline[0][1], Some(Rgb::BLACK));
+
+        // Inner corner of a room: the middle column is the farthest.
+        let corner = Matrix::from_rule(7, 7, |_, j| Some(1.0 / (0.5 + 0.1 * (j as f32 - 3.0).abs())));
+        let occluded = PostProcess::AmbientOcclusion { strength: 0.5 }.apply(&corner, &colors);
+        assert!(occluded[3][3].unwrap().r < 1.0);
+        assert_eq!(occluded[3][0], Some(Rgb::WHITE));
+    }
+
+    #[test]
+    fn parse() {
+        let passes = [
+            PostProcess::Outline,
+            PostProcess::AmbientOcclusion { strength: 0.5 },
+            PostProcess::Vignette { strength: 0.0 },
+            PostProcess::ToneMapping { white: 4.0 },
+            PostProcess::Gamma { gamma: 2.2 },
+            PostProcess::Scanlines { strength: 1.0 },
+        ];
+        for pass in passes {
+            assert_eq!(pass.to_string().parse::<PostProcess>(), Ok(pass));
+        }
+        assert!("outline 1".parse::<PostProcess>().is_err());
+        assert!("vignette".parse::<PostProcess>().is_err());
+        assert!("vignette 1.5".parse::<PostProcess>().is_err());
+        assert!("gamma 0".parse::<PostProcess>().is_err());
+        assert!("bloom 1".parse::<PostProcess>().is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+use std::str::FromStr;
+
+
+/// How camera maps directions of the world onto the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Projection {
+    /// Rays go through points of the flat image plane, straight lines stay straight.
+    #[default]
+    Perspective,
+    /// Parallel rays start on the rectangle of `width` in world units around camera.
+    Orthographic { width: f32 },
+    /// Equidistant fisheye: angle between ray and view direction grows linearly with distance
+    /// from the centre of the screen. Field of view may be 180 degrees and wider.
+    Fisheye,
+    /// Panorama of 360 degrees horizontally and 180 degrees vertically, camera field of view is ignored.
+    Equirectangular,
+}
+
+impl fmt::Display for Projection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Projection::Perspective => write!(f, "perspective"),
+            Projection::Orthographic { width } => write!(f, "orthographic {}", width),
+            Projection::Fisheye => write!(f, "fisheye"),
+            Projection::Equirectangular => write!(f, "equirectangular"),
+        }
+    }
+}
+
+impl FromStr for Projection {
+    type Err = String;
+
+    /// Parses `perspective`, `orthographic <width>`, `fisheye` or `equirectangular`.
+    fn from_str(s: &str) -> Result<Projection, String> {
+        let mut words = s.split_whitespace();
+        let projection = match (words.next(), words.next()) {
+            (Some("perspective"), None) => Projection::Perspective,
+            (Some("orthographic"), Some(width)) => {
+                let width: f32 = width.parse().map_err(|_| format!("Invalid orthographic width `{}`", width))?;
+                if width <= 0.0 || !width.is_finite() {
+                    return Err(format!("Orthographic width must be positive, got `{}`", width));
+                }
+                Projection::Orthographic { width }
+            }
+            (Some("fisheye"), None) => Projection::Fisheye,
+            (Some("equirectangular"), None) => Projection::Equirectangular,
+            _ => return Err(format!("Unknown projection `{}`", s)),
+        };
+        if words.next().is_some() {
+            return Err(format!("Unknown projection `{}`", s));
+        }
+        Ok(projection)
+    }
+}
+
+
+#[cfg(test)]
+mod projection_tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let projections = [
+            Projection::Perspective,
+            Projection::Orthographic { width: 4.5 },
+            Projection::Fisheye,
+            Projection::Equirectangular,
+        ];
+        for projection in projections {
+            assert_eq!(projection.to_string().parse::<Projection>(), Ok(projection));
+        }
+        assert!("orthographic".parse::<Projection>().is_err());
+        assert!("orthographic -1".parse::<Projection>().is_err());
+        assert!("fisheye 2".parse::<Projection>().is_err());
+        assert!("cylindrical".parse::<Projection>().is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use super::*;
+use crate::math::*;
+use crate::utils::solve_quadratic;
+
+
+/// Quadric surface `x^T * Q * x = 0` where `x` is a point in homogeneous coordinates
+/// and `Q` is a symmetric matrix of size `(n + 1) x (n + 1)` for n-dimensional space.
+///
+/// The surface is placed at `transform.position` and scaled by `transform.scale`,
+/// axes of the surface are the axes of world.
+#[derive(Debug, Clone)]
+pub struct Quadric {
+    matrix: Matrix<f32>,
+}
+
+impl Quadric {
+    /// Creates quadric from symmetric matrix.
+    /// If matrix is not symmetric or smaller than 2x2 returns `None`.
+    pub fn new(matrix: Matrix<f32>) -> Option<Quadric> {
+        if matrix.rows_count() < 2 || !matrix.is_symmetric(1e-6) {
+            return None;
+        }
+        Some(Quadric { matrix })
+    }
+
+    /// Creates quadric `sum(coefficients[i] * x_i^2) + linear * x_n + constant = 0`,
+    /// where `x_n` is the last coordinate. `coefficients` must not be empty.
+    fn from_coefficients(coefficients: Vec<f32>, linear: f32, constant: f32) -> Quadric {
+        let dim = coefficients.len();
+        let mut matrix = Matrix::from_diagonal(&Vector::new(coefficients).extended(constant));
+        matrix.set(dim - 1, dim, 0.5 * linear).unwrap();
+        matrix.set(dim, dim - 1, 0.5 * linear).unwrap();
+        Quadric { matrix }
+    }
+
+    /// Sphere `x^2 + y^2 + z^2 = r^2`.
+    pub fn sphere3d(r: f32) -> Quadric {
+        Quadric::ellipsoid3d(r, r, r)
+    }
+
+    /// Ellipsoid `x^2 / a^2 + y^2 / b^2 + z^2 / c^2 = 1`.
+    pub fn ellipsoid3d(a: f32, b: f32, c: f32) -> Quadric {
+        Quadric::from_coefficients(vec![1.0 / (a * a), 1.0 / (b * b), 1.0 / (c * c)], 0.0, -1.0)
+    }
+
+    /// Elliptic paraboloid `x^2 / a^2 + y^2 / b^2 = z`.
+    pub fn elliptic_paraboloid3d(a: f32, b: f32) -> Quadric {
+        Quadric::from_coefficients(vec![1.0 / (a * a), 1.0 / (b * b), 0.0], -1.0, 0.0)
+    }
+
+    /// Hyperbolic paraboloid (saddle) `x^2 / a^2 - y^2 / b^2 = z`.
+    pub fn hyperbolic_paraboloid3d(a: f32, b: f32) -> Quadric {
+        Quadric::from_coefficients(vec![1.0 / (a * a), -1.0 / (b * b), 0.0], -1.0, 0.0)
+    }
+
+    /// Hyperboloid of one sheet `x^2 / a^2 + y^2 / b^2 - z^2 / c^2 = 1`.
+    pub fn hyperboloid_one_sheet3d(a: f32, b: f32, c: f32) -> Quadric {
+        Quadric::from_coefficients(vec![1.0 / (a * a), 1.0 / (b * b), -1.0 / (c * c)], 0.0, -1.0)
+    }
+
+    /// Hyperboloid of two sheets `x^2 / a^2 + y^2 / b^2 - z^2 / c^2 = -1`.
+    pub fn hyperboloid_two_sheets3d(a: f32, b: f32, c: f32) -> Quadric {
+        Quadric::from_coefficients(vec![1.0 / (a * a), 1.0 / (b * b), -1.0 / (c * c)], 0.0, 1.0)
+    }
+
+    /// Elliptic cone `x^2 / a^2 + y^2 / b^2 = z^2 / c^2`.
+    pub fn cone3d(a: f32, b: f32, c: f32) -> Quadric {
+        Quadric::from_coefficients(vec![1.0 / (a * a), 1.0 / (b * b), -1.0 / (c * c)], 0.0, 0.0)
+    }
+
+    /// Elliptic cylinder along z axis `x^2 / a^2 + y^2 / b^2 = 1`.
+    pub fn cylinder3d(a: f32, b: f32) -> Quadric {
+        Quadric::from_coefficients(vec![1.0 / (a * a), 1.0 / (b * b), 0.0], 0.0, -1.0)
+    }
+
+    pub fn matrix(&self) -> &Matrix<f32> {
+        &self.matrix
+    }
+
+    /// Returns dimension of space where quadric lives.
+    pub fn dim(&self) -> usize {
+        self.matrix.rows_count() - 1
+    }
+}
+
+impl TryFrom<&Hyperellipsoid> for Quadric {
+    type Error = ();
+
+    /// Hyperellipsoid `sum(x_i^2 / a_i^2) = 1` with the same semiaxes.
+    /// Hyperellipsoid without semiaxes has no quadric, then error is returned.
+    fn try_from(hyperellipsoid: &Hyperellipsoid) -> Result<Quadric, ()> {
+        if hyperellipsoid.semiaxes.is_empty() {
+            return Err(());
+        }
+        let coefficients = hyperellipsoid.semiaxes
+            .iter()
+            .map(|a| 1.0 / (a * a))
+            .collect();
+        Ok(Quadric::from_coefficients(coefficients, 0.0, -1.0))
+    }
+}
+
+impl Quadric {
+    /// Returns coefficients `(a, b, c)` of equation `a * t^2 + b * t + c = 0`
+    /// which parameter `t` of the ray satisfies on the surface.
+    /// If dimensions of `ray` and quadric are not the same returns `None`.
+    fn ray_equation(&self, transform: &Transform, ray: &Ray) -> Option<(f32, f32, f32)> {
+        if ray.point.dim() != self.dim() || ray.direction.dim() != self.dim() ||
+            transform.position.dim() != self.dim() {
+            return None;
+        }
+
+        // Point of ray in homogeneous coordinates relative to the quadric position and scale.
+        let ray = transform.to_local_ray(ray)?;
+        let p = ray.point.extended(1.0);
+        // Direction is a point at infinity.
+        let d = ray.direction.extended(0.0);
+
+        // Substitute p + t * d into x^T * Q * x = 0:
+        // (d^T Q d) * t^2 + 2 * (d^T Q p) * t + p^T Q p = 0.
+        let a = self.matrix.bilinear_form(&d, &d)?;
+        let b = 2.0 * self.matrix.bilinear_form(&d, &p)?;
+        let c = self.matrix.bilinear_form(&p, &p)?;
+        Some((a, b, c))
+    }
+}
+
+impl GameObject for Quadric {
+    /// If dimensions of `ray` and quadric are not the same returns `None`.
+    /// Returns the nearest non-negative distance or `None` if there is no such intersection.
+    fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        let (a, b, c) = self.ray_equation(transform, ray)?;
+        let (t1, t2) = solve_quadratic(a, b, c)?;
+        if t1 >= 0.0 {
+            return Some(t1);
+        }
+        if t2 >= 0.0 {
+            return Some(t2);
+        }
+        None
+    }
+
+    /// Inside of quadric is where `x^T * Q * x < 0`,
+    /// e.g. inside of ellipsoid, cylinder or cone and above paraboloid.
+    fn intersection_intervals(&self, transform: &Transform, ray: &Ray) -> Vec<Interval> {
+        let (a, b, c) = match self.ray_equation(transform, ray) {
+            None => return vec![],
+            Some(coefficients) => coefficients,
+        };
+        match solve_quadratic(a, b, c) {
+            // Sign of the left side of equation is the same for all `t`.
+            None => if c < 0.0 { vec![Interval::everything()] } else { vec![] },
+            // Equation is linear: b * t + c < 0.
+            Some((t, _)) if a.abs() < f32::EPSILON => if b > 0.0 {
+                vec![Interval::new(f32::NEG_INFINITY, t)]
+            } else {
+                vec![Interval::new(t, f32::INFINITY)]
+            },
+            Some((t1, t2)) => if a > 0.0 {
+                vec![Interval::new(t1, t2)]
+            } else {
+                vec![Interval::new(f32::NEG_INFINITY, t1), Interval::new(t2, f32::INFINITY)]
+            },
+        }
+    }
+
+    /// Normal is the gradient of `x^T * Q * x`, it points out of the inside of quadric.
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        let point = transform.to_local_point(point)?;
+        if point.dim() != self.dim() {
+            return None;
+        }
+        let p = point.extended(1.0);
+        // First n components of 2 * Q * p, factor 2 is dropped by normalization.
+        let mut normal = Vector::new(
+            (0..self.dim()).map(|i| (0..p.dim()).map(|j| self.matrix[i][j] * p[j]).sum()).collect()
+        );
+        normal.normalize().then_some(normal)
+    }
+}
+
+
+#[cfg(test)]
+mod quadric_tests {
+    use crate::utils::assert_eq_option_f32;
+
+    use super::*;
+
+    fn test(
+        quadric: &Quadric,
+        point: [f32; 3],
+        direction: [f32; 3],
+        expected_distance: Option<f32>,
+    ) {
+        let r = Ray::new(Vector::new(point.to_vec()), Vector::new(direction.to_vec()));
+        assert_eq_option_f32(quadric.intersection_distance(&Transform::default(), &r), expected_distance);
+    }
+
+    #[test]
+    fn non_symmetric_matrix() {
+        let m = Matrix::new(2, 2, vec![
+            1.0, 2.0,
+            0.0, 1.0,
+        ]).unwrap();
+        assert!(Quadric::new(m).is_none());
+    }
+
+    #[test]
+    fn from_hyperellipsoid() {
+        let e = Hyperellipsoid::new3d(1.0, 2.0, 3.0);
+        let q = Quadric::try_from(&e).unwrap();
+        let r = Ray::new(Vector::from_xyz(-5.0, 0.5, 0.5), Vector::from_xyz(1.0, 0.1, 0.2));
+        assert_eq_option_f32(
+            q.intersection_distance(&Transform::default(), &r),
+            e.intersection_distance(&Transform::default(), &r),
+        );
+        assert!(Quadric::try_from(&Hyperellipsoid::new(vec![])).is_err());
+    }
+
+    #[test]
+    fn sphere_from_outside() {
+        test(&Quadric::sphere3d(1.0), [-3.0, 0.0, 0.0], [1.0, 0.0, 0.0], Some(2.0));
+    }
+
+    #[test]
+    fn sphere_from_inside() {
+        test(&Quadric::sphere3d(2.0), [0.0, 0.0, 0.0], [0.0, 1.0, 0.0], Some(2.0));
+    }
+
+    #[test]
+    fn sphere_behind() {
+        test(&Quadric::sphere3d(1.0), [3.0, 0.0, 0.0], [1.0, 0.0, 0.0], None);
+    }
+
+    #[test]
+    fn sphere_moved() {
+        let t = Transform::new_from_coords(
+            0.0, 5.0, 0.0,
+            1.0, 0.0, 0.0,
+        ).unwrap();
+        let r = Ray::new(Vector::zero3(), Vector::from_xyz(0.0, 1.0, 0.0));
+        assert_eq_option_f32(Quadric::sphere3d(1.0).intersection_distance(&t, &r), Some(4.0));
+    }
+
+    #[test]
+    fn cylinder_side() {
+        test(&Quadric::cylinder3d(1.0, 1.0), [-3.0, 0.0, 100.0], [1.0, 0.0, 0.0], Some(2.0));
+    }
+
+    #[test]
+    fn cylinder_along_axis() {
+        test(&Quadric::cylinder3d(1.0, 1.0), [0.0, 0.0, -3.0], [0.0, 0.0, 1.0], None);
+    }
+
+    #[test]
+    fn paraboloid_from_above() {
+        test(&Quadric::elliptic_paraboloid3d(1.0, 1.0), [0.0, 0.0, 5.0], [0.0, 0.0, -1.0], Some(5.0));
+    }
+
+    #[test]
+    fn paraboloid_side() {
+        // x^2 = z, z = 4 => x = -2.
+        test(&Quadric::elliptic_paraboloid3d(1.0, 1.0), [-5.0, 0.0, 4.0], [1.0, 0.0, 0.0], Some(3.0));
+    }
+
+    #[test]
+    fn cone_side() {
+        // x^2 = z^2, z = 2 => x = -2.
+        test(&Quadric::cone3d(1.0, 1.0, 1.0), [-5.0, 0.0, 2.0], [1.0, 0.0, 0.0], Some(3.0));
+    }
+
+    #[test]
+    fn hyperboloid_one_sheet_waist() {
+        test(&Quadric::hyperboloid_one_sheet3d(1.0, 1.0, 1.0), [-5.0, 0.0, 0.0], [1.0, 0.0, 0.0], Some(4.0));
+    }
+
+    #[test]
+    fn hyperboloid_two_sheets_gap() {
+        test(&Quadric::hyperboloid_two_sheets3d(1.0, 1.0, 1.0), [-5.0, 0.0, 0.0], [1.0, 0.0, 0.0], None);
+        test(&Quadric::hyperboloid_two_sheets3d(1.0, 1.0, 1.0), [0.0, 0.0, -5.0], [0.0, 0.0, 1.0], Some(4.0));
+    }
+
+    #[test]
+    fn intervals_sphere() {
+        let r = Ray::new(Vector::from_xyz(-3.0, 0.0, 0.0), Vector::from_xyz(1.0, 0.0, 0.0));
+        let intervals = Quadric::sphere3d(1.0).intersection_intervals(&Transform::default(), &r);
+        assert_eq!(intervals, vec![Interval::new(2.0, 4.0)]);
+    }
+
+    #[test]
+    fn intervals_cone_along_axis() {
+        // Ray goes through both nappes of the cone and outside between them.
+        let r = Ray::new(Vector::from_xyz(0.5, 0.0, -3.0), Vector::from_xyz(0.0, 0.0, 1.0));
+        let intervals = Quadric::cone3d(1.0, 1.0, 1.0).intersection_intervals(&Transform::default(), &r);
+        assert_eq!(intervals, vec![
+            Interval::new(f32::NEG_INFINITY, 2.5),
+            Interval::new(3.5, f32::INFINITY),
+        ]);
+    }
+
+    #[test]
+    fn intervals_inside_cylinder() {
+        let r = Ray::new(Vector::zero3(), Vector::from_xyz(0.0, 0.0, 1.0));
+        let intervals = Quadric::cylinder3d(1.0, 1.0).intersection_intervals(&Transform::default(), &r);
+        assert_eq!(intervals, vec![Interval::everything()]);
+    }
+
+    #[test]
+    fn normals() {
+        let t = Transform::new_from_coords(0.0, 5.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        let n = Quadric::sphere3d(2.0).normal(&t, &Vector::from_xyz(0.0, 3.0, 0.0)).unwrap();
+        assert!(n.approximately_equal(&Vector::from_xyz(0.0, -1.0, 0.0), 1e-6));
+        let n = Quadric::cylinder3d(1.0, 1.0).normal(&Transform::default(), &Vector::from_xyz(1.0, 0.0, 7.0)).unwrap();
+        assert!(n.approximately_equal(&Vector::from_xyz(1.0, 0.0, 0.0), 1e-6));
+    }
+}
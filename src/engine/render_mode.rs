@@ -0,0 +1,64 @@
+use std::fmt;
+use std::str::FromStr;
+
+
+/// How pixels of 3d `Canvas` are mapped onto terminal cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// One pixel per cell drawn with a character of ASCII ramp.
+    #[default]
+    Ascii,
+    /// Two pixels per cell, one above another, drawn with `▀` in foreground and background colours.
+    HalfBlock,
+    /// 2×4 pixels per cell drawn with dots of Braille pattern.
+    Braille,
+}
+
+impl RenderMode {
+    /// Returns number of canvas pixels per terminal cell: (columns, rows).
+    pub fn cell_pixels(&self) -> (usize, usize) {
+        match self {
+            RenderMode::Ascii => (1, 1),
+            RenderMode::HalfBlock => (1, 2),
+            RenderMode::Braille => (2, 4),
+        }
+    }
+}
+
+impl fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RenderMode::Ascii => "ascii",
+            RenderMode::HalfBlock => "half_block",
+            RenderMode::Braille => "braille",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RenderMode, String> {
+        match s {
+            "ascii" => Ok(RenderMode::Ascii),
+            "half_block" => Ok(RenderMode::HalfBlock),
+            "braille" => Ok(RenderMode::Braille),
+            _ => Err(format!("Unknown render mode `{}`", s)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod render_mode_tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for mode in [RenderMode::Ascii, RenderMode::HalfBlock, RenderMode::Braille] {
+            assert_eq!(mode.to_string().parse::<RenderMode>(), Ok(mode));
+        }
+        assert!("quarter_block".parse::<RenderMode>().is_err());
+    }
+}
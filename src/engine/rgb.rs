@@ -0,0 +1,116 @@
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+use console_engine::Color;
+
+
+/// Linear colour with channels usually in `[0, 1]`.
+/// Channels may exceed 1 while light is accumulated, they are clamped on conversion to terminal colour.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rgb {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb { r: 0.0, g: 0.0, b: 0.0 };
+    pub const WHITE: Rgb = Rgb { r: 1.0, g: 1.0, b: 1.0 };
+
+    pub fn new(r: f32, g: f32, b: f32) -> Rgb {
+        Rgb { r, g, b }
+    }
+
+    /// Creates colour with all channels equal to `value`.
+    pub fn grey(value: f32) -> Rgb {
+        Rgb::new(value, value, value)
+    }
+
+    /// Creates colour from 8-bit channels.
+    pub fn from_u8(r: u8, g: u8, b: u8) -> Rgb {
+        Rgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+    }
+
+    /// Returns 8-bit channels, channels out of `[0, 1]` are clamped.
+    pub fn to_u8(&self) -> (u8, u8, u8) {
+        let channel = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+        (channel(self.r), channel(self.g), channel(self.b))
+    }
+
+    /// Returns relative luminance (Rec. 709).
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// Returns colour between `self` (`t = 0`) and `other` (`t = 1`).
+    pub fn lerp(&self, other: &Rgb, t: f32) -> Rgb {
+        *self * (1.0 - t) + *other * t
+    }
+
+    /// Returns true colour of terminal.
+    pub fn to_console_color(&self) -> Color {
+        let (r, g, b) = self.to_u8();
+        Color::Rgb { r, g, b }
+    }
+}
+
+impl Add for Rgb {
+    type Output = Rgb;
+
+    fn add(self, other: Rgb) -> Rgb {
+        Rgb::new(self.r + other.r, self.g + other.g, self.b + other.b)
+    }
+}
+
+impl AddAssign for Rgb {
+    fn add_assign(&mut self, other: Rgb) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Rgb {
+    type Output = Rgb;
+
+    fn sub(self, other: Rgb) -> Rgb {
+        Rgb::new(self.r - other.r, self.g - other.g, self.b - other.b)
+    }
+}
+
+/// Multiplies channels: light of colour `self` reflected by surface of colour `other`.
+impl Mul for Rgb {
+    type Output = Rgb;
+
+    fn mul(self, other: Rgb) -> Rgb {
+        Rgb::new(self.r * other.r, self.g * other.g, self.b * other.b)
+    }
+}
+
+impl Mul<f32> for Rgb {
+    type Output = Rgb;
+
+    fn mul(self, k: f32) -> Rgb {
+        Rgb::new(self.r * k, self.g * k, self.b * k)
+    }
+}
+
+
+#[cfg(test)]
+mod rgb_tests {
+    use super::*;
+
+    #[test]
+    fn operations() {
+        let a = Rgb::new(0.5, 0.25, 1.0);
+        assert_eq!(a + Rgb::grey(0.25), Rgb::new(0.75, 0.5, 1.25));
+        assert_eq!(a - Rgb::grey(0.25), Rgb::new(0.25, 0.0, 0.75));
+        assert_eq!(a * Rgb::new(0.5, 2.0, 0.0), Rgb::new(0.25, 0.5, 0.0));
+        assert_eq!(a * 2.0, Rgb::new(1.0, 0.5, 2.0));
+        assert_eq!(Rgb::BLACK.lerp(&Rgb::WHITE, 0.25), Rgb::grey(0.25));
+    }
+
+    #[test]
+    fn to_console_color() {
+        assert_eq!(Rgb::new(1.5, 0.5, -1.0).to_console_color(), Color::Rgb { r: 255, g: 128, b: 0 });
+        assert_eq!(Rgb::from_u8(255, 0, 51), Rgb::new(1.0, 0.0, 0.2));
+        assert!((Rgb::WHITE.luminance() - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,379 @@
+use super::*;
+use crate::math::*;
+
+
+/// Signed distance field: distance from a point to the nearest surface of a shape.
+/// Distance is negative inside of the shape.
+///
+/// Distance may be underestimated but must never be overestimated,
+/// otherwise sphere tracing can step through the surface.
+pub trait Sdf {
+    fn distance(&self, point: &Vector<f32>) -> f32;
+}
+
+/// Linear interpolation between `a` and `b`.
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a * (1.0 - t) + b * t
+}
+
+/// Hypersphere with center in the origin.
+#[derive(Debug, Clone)]
+pub struct SdfSphere {
+    pub radius: f32,
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        point.magnitude() - self.radius
+    }
+}
+
+/// Axis aligned hyperbox with center in the origin.
+/// Dimension of box is the dimension of `half_extents`.
+#[derive(Debug, Clone)]
+pub struct SdfBox {
+    pub half_extents: Vector<f32>,
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        let q: Vec<f32> = point.elements
+            .iter()
+            .zip(self.half_extents.elements.iter())
+            .map(|(x, b)| x.abs() - b)
+            .collect();
+        let outside = q.iter().map(|x| x.max(0.0) * x.max(0.0)).sum::<f32>().sqrt();
+        let inside = q.iter().copied().fold(f32::NEG_INFINITY, f32::max).min(0.0);
+        outside + inside
+    }
+}
+
+/// 3d torus lying in xy plane with center in the origin.
+#[derive(Debug, Clone)]
+pub struct SdfTorus {
+    /// Distance from the center of torus to the center of tube.
+    pub major_radius: f32,
+    /// Radius of tube.
+    pub minor_radius: f32,
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        let xy = (point[0] * point[0] + point[1] * point[1]).sqrt() - self.major_radius;
+        (xy * xy + point[2] * point[2]).sqrt() - self.minor_radius
+    }
+}
+
+/// 3d Mandelbulb fractal with center in the origin, its radius is about 1.2.
+#[derive(Debug, Clone)]
+pub struct Mandelbulb {
+    pub power: f32,
+    pub iterations: usize,
+}
+
+impl Default for Mandelbulb {
+    fn default() -> Self {
+        Mandelbulb { power: 8.0, iterations: 8 }
+    }
+}
+
+impl Sdf for Mandelbulb {
+    /// Distance estimator from http://blog.hvidtfeldts.net/index.php/2011/09/distance-estimated-3d-fractals-v-the-mandelbulb-different-de-approximations/
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        let c = [point[0], point[1], point[2]];
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = 0.0;
+        for _ in 0..self.iterations {
+            r = (z[0] * z[0] + z[1] * z[1] + z[2] * z[2]).sqrt();
+            if r > 2.0 {
+                break;
+            }
+            let theta = if r > 0.0 { (z[2] / r).acos() } else { 0.0 };
+            let phi = z[1].atan2(z[0]);
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+
+            let zr = r.powf(self.power);
+            let (theta, phi) = (theta * self.power, phi * self.power);
+            z = [
+                zr * theta.sin() * phi.cos() + c[0],
+                zr * theta.sin() * phi.sin() + c[1],
+                zr * theta.cos() + c[2],
+            ];
+        }
+        if r == 0.0 {
+            return 0.0;
+        }
+        0.5 * r.ln() * r / dr
+    }
+}
+
+/// Union of two fields with smooth blending of size `k` where they meet.
+pub struct SmoothUnion {
+    pub a: Box<dyn Sdf>,
+    pub b: Box<dyn Sdf>,
+    pub k: f32,
+}
+
+impl Sdf for SmoothUnion {
+    /// https://iquilezles.org/articles/smin/
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        let (d1, d2) = (self.a.distance(point), self.b.distance(point));
+        let h = (0.5 + 0.5 * (d2 - d1) / self.k).clamp(0.0, 1.0);
+        mix(d2, d1, h) - self.k * h * (1.0 - h)
+    }
+}
+
+/// Field `a` without field `b` with smooth blending of size `k` where they meet.
+pub struct SmoothSubtraction {
+    pub a: Box<dyn Sdf>,
+    pub b: Box<dyn Sdf>,
+    pub k: f32,
+}
+
+impl Sdf for SmoothSubtraction {
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        let (d1, d2) = (self.b.distance(point), self.a.distance(point));
+        let h = (0.5 - 0.5 * (d2 + d1) / self.k).clamp(0.0, 1.0);
+        mix(d2, -d1, h) + self.k * h * (1.0 - h)
+    }
+}
+
+/// Field moved by `offset`.
+pub struct Translation {
+    pub sdf: Box<dyn Sdf>,
+    pub offset: Vector<f32>,
+}
+
+impl Sdf for Translation {
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        self.sdf.distance(&(point - &self.offset).unwrap())
+    }
+}
+
+/// Field repeated infinitely with `period` along each axis.
+/// Zero period means no repetition along the axis.
+/// Shape must fit in one cell of repetition.
+pub struct Repetition {
+    pub sdf: Box<dyn Sdf>,
+    pub period: Vector<f32>,
+}
+
+impl Sdf for Repetition {
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        let cell_point = Vector::new(point.elements
+            .iter()
+            .zip(self.period.elements.iter())
+            .map(|(x, period)| if *period > 0.0 { x - period * (x / period).round() } else { *x })
+            .collect());
+        self.sdf.distance(&cell_point)
+    }
+}
+
+/// 3d field twisted around z axis on `rate` radians per unit of height.
+/// Twisting stretches the field, so distance is divided by `lipschitz`
+/// to keep sphere tracing from stepping through the surface.
+/// `sqrt(1 + (rate * r)^2)` is enough for a shape within radius `r` from z axis.
+pub struct Twist {
+    pub sdf: Box<dyn Sdf>,
+    pub rate: f32,
+    pub lipschitz: f32,
+}
+
+impl Sdf for Twist {
+    fn distance(&self, point: &Vector<f32>) -> f32 {
+        let angle = -self.rate * point[2];
+        let (cos, sin) = (angle.cos(), angle.sin());
+        let twisted = Vector::from_xyz(
+            cos * point[0] - sin * point[1],
+            sin * point[0] + cos * point[1],
+            point[2],
+        );
+        self.sdf.distance(&twisted) / self.lipschitz
+    }
+}
+
+/// Game object made of signed distance field.
+/// Intersections are found with sphere tracing: ray moves forward by the distance to the field
+/// until it is closer than `epsilon` to the surface.
+/// Field is placed at `transform.position`.
+pub struct SphereTraced {
+    pub sdf: Box<dyn Sdf>,
+    /// Maximal number of steps along one ray.
+    pub max_steps: usize,
+    /// Distance to the surface which counts as a hit.
+    pub epsilon: f32,
+    /// Ray is missed when it goes further than this distance.
+    pub max_distance: f32,
+}
+
+impl SphereTraced {
+    pub fn new(sdf: Box<dyn Sdf>) -> SphereTraced {
+        SphereTraced { sdf, max_steps: 64, epsilon: 1e-3, max_distance: 100.0 }
+    }
+}
+
+impl GameObject for SphereTraced {
+    fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        let ray = transform.to_local_ray(ray)?;
+        let direction_magnitude = ray.direction.magnitude();
+        if direction_magnitude == 0.0 {
+            return None;
+        }
+        let direction = &ray.direction / direction_magnitude;
+
+        // Distance along the normalized direction.
+        let mut t = 0.0;
+        for _ in 0..self.max_steps {
+            let point = (&ray.point + &(&direction * t))?;
+            let distance = self.sdf.distance(&point);
+            if distance.abs() < self.epsilon {
+                // Ray parameter is measured in lengths of the ray direction.
+                return Some(t / direction_magnitude);
+            }
+            t += distance.abs();
+            if t > self.max_distance {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Normal is the gradient of distance field estimated with central differences.
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        let point = transform.to_local_point(point)?;
+        let mut normal = Vector::new(
+            (0..point.dim())
+                .map(|i| {
+                    let mut forward = point.clone();
+                    forward[i] += self.epsilon;
+                    let mut backward = point.clone();
+                    backward[i] -= self.epsilon;
+                    self.sdf.distance(&forward) - self.sdf.distance(&backward)
+                })
+                .collect()
+        );
+        normal.normalize().then_some(normal)
+    }
+}
+
+
+#[cfg(test)]
+mod sdf_tests {
+    use crate::utils::{assert_eq_f32, assert_eq_option_f32};
+
+    use super::*;
+
+    fn sphere(radius: f32) -> Box<dyn Sdf> {
+        Box::new(SdfSphere { radius })
+    }
+
+    #[test]
+    fn box_distance() {
+        let b = SdfBox { half_extents: Vector::from_xyz(1.0, 2.0, 3.0) };
+        assert_eq_f32(b.distance(&Vector::from_xyz(3.0, 0.0, 0.0)), 2.0);
+        assert_eq_f32(b.distance(&Vector::from_xyz(2.0, 3.0, 0.0)), 2.0_f32.sqrt());
+        assert_eq_f32(b.distance(&Vector::zero3()), -1.0);
+    }
+
+    #[test]
+    fn box4d_distance() {
+        let b = SdfBox { half_extents: Vector::new(vec![1.0, 1.0, 1.0, 1.0]) };
+        assert_eq_f32(b.distance(&Vector::new(vec![0.0, 0.0, 0.0, 4.0])), 3.0);
+    }
+
+    #[test]
+    fn torus_distance() {
+        let t = SdfTorus { major_radius: 2.0, minor_radius: 0.5 };
+        assert_eq_f32(t.distance(&Vector::from_xyz(2.0, 0.0, 0.0)), -0.5);
+        assert_eq_f32(t.distance(&Vector::zero3()), 1.5);
+    }
+
+    #[test]
+    fn smooth_union_is_not_greater_than_union() {
+        let u = SmoothUnion {
+            a: sphere(1.0),
+            b: Box::new(Translation { sdf: sphere(1.0), offset: Vector::from_xyz(1.5, 0.0, 0.0) }),
+            k: 0.5,
+        };
+        let p = Vector::from_xyz(0.75, 1.0, 0.0);
+        let hard = f32::min(sphere(1.0).distance(&p), (p.distance(&Vector::from_xyz(1.5, 0.0, 0.0))) - 1.0);
+        assert!(u.distance(&p) < hard);
+        // Far from the seam union is not changed.
+        assert_eq_f32(u.distance(&Vector::from_xyz(-3.0, 0.0, 0.0)), 2.0);
+    }
+
+    #[test]
+    fn smooth_subtraction() {
+        let s = SmoothSubtraction { a: sphere(2.0), b: sphere(1.0), k: 0.1 };
+        assert!(s.distance(&Vector::zero3()) > 0.0);
+        assert!(s.distance(&Vector::from_xyz(1.5, 0.0, 0.0)) < 0.0);
+    }
+
+    #[test]
+    fn repetition() {
+        let r = Repetition { sdf: sphere(1.0), period: Vector::from_xyz(4.0, 4.0, 0.0) };
+        assert_eq_f32(r.distance(&Vector::from_xyz(8.0, -4.0, 0.0)), -1.0);
+        assert_eq_f32(r.distance(&Vector::from_xyz(2.0, 0.0, 0.0)), 1.0);
+        assert_eq_f32(r.distance(&Vector::from_xyz(0.0, 0.0, 4.0)), 3.0);
+    }
+
+    #[test]
+    fn twist_without_rate() {
+        let b = || Box::new(SdfBox { half_extents: Vector::from_xyz(1.0, 2.0, 3.0) });
+        let t = Twist { sdf: b(), rate: 0.0, lipschitz: 1.0 };
+        let p = Vector::from_xyz(2.0, 3.0, 1.0);
+        assert_eq_f32(t.distance(&p), b().distance(&p));
+    }
+
+    #[test]
+    fn twist_quarter_turn() {
+        let b = SdfBox { half_extents: Vector::from_xyz(3.0, 1.0, 10.0) };
+        let t = Twist { sdf: Box::new(b), rate: std::f32::consts::PI / 2.0, lipschitz: 1.0 };
+        // At height 1 the box is turned on 90 degrees.
+        assert!(t.distance(&Vector::from_xyz(0.0, 2.5, 1.0)) < 0.0);
+        assert!(t.distance(&Vector::from_xyz(2.5, 0.0, 1.0)) > 0.0);
+    }
+
+    #[test]
+    fn mandelbulb_inside_and_outside() {
+        let m = Mandelbulb::default();
+        assert!(m.distance(&Vector::zero3()) <= 0.0);
+        assert!(m.distance(&Vector::from_xyz(3.0, 0.0, 0.0)) > 1.0);
+    }
+
+    #[test]
+    fn sphere_traced_sphere() {
+        let s = SphereTraced::new(sphere(1.0));
+        let t = Transform::new_from_coords(5.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        let r = Ray::new(Vector::zero3(), Vector::from_xyz(2.0, 0.0, 0.0));
+        let distance = s.intersection_distance(&t, &r).unwrap();
+        assert!((distance - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn sphere_traced_miss() {
+        let s = SphereTraced::new(sphere(1.0));
+        let r = Ray::new(Vector::from_xyz(-5.0, 3.0, 0.0), Vector::from_xyz(1.0, 0.0, 0.0));
+        assert_eq_option_f32(s.intersection_distance(&Transform::default(), &r), None);
+    }
+
+    #[test]
+    fn sphere_traced_step_budget() {
+        let mut s = SphereTraced::new(sphere(1.0));
+        // Ray grazes the sphere so many small steps are needed.
+        let r = Ray::new(Vector::from_xyz(-50.0, 1.0005, 0.0), Vector::from_xyz(1.0, 0.0, 0.0));
+        s.max_steps = 2;
+        assert_eq_option_f32(s.intersection_distance(&Transform::default(), &r), None);
+        s.max_steps = 1000;
+        assert!(s.intersection_distance(&Transform::default(), &r).is_some());
+    }
+
+    #[test]
+    fn sphere_traced_normal() {
+        let traced = SphereTraced::new(sphere(1.0));
+        let t = Transform::new_from_coords(0.0, 0.0, 2.0, 1.0, 0.0, 0.0).unwrap();
+        let n = traced.normal(&t, &Vector::from_xyz(0.0, 0.0, 3.0)).unwrap();
+        assert!(n.approximately_equal(&Vector::from_xyz(0.0, 0.0, 1.0), 1e-3));
+    }
+}
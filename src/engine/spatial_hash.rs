@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use crate::math::*;
+
+
+/// Coordinates of a cell of `SpatialHash`.
+pub type CellKey = Vec<i64>;
+
+/// Object stored in `SpatialHash` with the range of cells it covers.
+#[derive(Debug, Clone)]
+struct SpatialHashEntry {
+    aabb: Aabb,
+    min_cell: CellKey,
+    max_cell: CellKey,
+}
+
+/// Uniform grid of cubic cells stored in a hash map, for proximity and broad phase collision queries.
+///
+/// Objects are referred by indices, e.g. indices in `Game::game_objects`,
+/// and stored in every cell their bounding box overlaps.
+/// Objects without bounding box are not stored.
+#[derive(Debug, Clone)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<CellKey, Vec<usize>>,
+    entries: HashMap<usize, SpatialHashEntry>,
+}
+
+impl SpatialHash {
+    /// Creates empty hash with cells of the given edge length.
+    /// If `cell_size` is not positive returns `None`.
+    pub fn new(cell_size: f32) -> Option<SpatialHash> {
+        if cell_size <= 0.0 || !cell_size.is_finite() {
+            return None;
+        }
+        Some(SpatialHash { cell_size, cells: HashMap::new(), entries: HashMap::new() })
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Returns number of stored objects.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns key of the cell which contains `point`.
+    pub fn cell_key(&self, point: &Vector<f32>) -> CellKey {
+        (0..point.dim()).map(|i| (point[i] / self.cell_size).floor() as i64).collect()
+    }
+
+    /// Calls `f` with key of every cell from `min_cell` to `max_cell` inclusive.
+    fn for_each_cell(min_cell: &CellKey, max_cell: &CellKey, mut f: impl FnMut(&CellKey)) {
+        if min_cell.iter().zip(max_cell).any(|(min, max)| min > max) {
+            return;
+        }
+        let mut key = min_cell.clone();
+        loop {
+            f(&key);
+            // Increment key like a number with digits in ranges `min_cell[i]..=max_cell[i]`.
+            let mut i = 0;
+            loop {
+                if i == key.len() {
+                    return;
+                }
+                if key[i] < max_cell[i] {
+                    key[i] += 1;
+                    break;
+                }
+                key[i] = min_cell[i];
+                i += 1;
+            }
+        }
+    }
+
+    /// Inserts object or moves it if it is already stored.
+    /// Object is reinserted into cells only when the range of its cells changes.
+    /// If `aabb` is `None` object is removed.
+    pub fn update(&mut self, id: usize, aabb: Option<Aabb>) {
+        let aabb = match aabb {
+            None => {
+                self.remove(id);
+                return;
+            }
+            Some(aabb) => aabb,
+        };
+        let min_cell = self.cell_key(&aabb.min);
+        let max_cell = self.cell_key(&aabb.max);
+        if let Some(entry) = self.entries.get_mut(&id) {
+            if entry.min_cell == min_cell && entry.max_cell == max_cell {
+                entry.aabb = aabb;
+                return;
+            }
+            self.remove(id);
+        }
+
+        let cells = &mut self.cells;
+        SpatialHash::for_each_cell(&min_cell, &max_cell, |key| {
+            cells.entry(key.clone()).or_default().push(id);
+        });
+        self.entries.insert(id, SpatialHashEntry { aabb, min_cell, max_cell });
+    }
+
+    /// Removes object. Returns false if object was not stored.
+    pub fn remove(&mut self, id: usize) -> bool {
+        let entry = match self.entries.remove(&id) {
+            None => return false,
+            Some(entry) => entry,
+        };
+        let cells = &mut self.cells;
+        SpatialHash::for_each_cell(&entry.min_cell, &entry.max_cell, |key| {
+            if let Some(ids) = cells.get_mut(key) {
+                ids.retain(|other| *other != id);
+                if ids.is_empty() {
+                    cells.remove(key);
+                }
+            }
+        });
+        true
+    }
+
+    /// Synchronizes hash with objects which have indices in `boxes`:
+    /// moves objects with changed boxes and removes objects with indices out of `boxes`.
+    pub fn update_all(&mut self, boxes: &[Option<Aabb>]) {
+        let removed: Vec<usize> = self.entries.keys().filter(|id| **id >= boxes.len()).copied().collect();
+        for id in removed {
+            self.remove(id);
+        }
+        for (id, aabb) in boxes.iter().enumerate() {
+            self.update(id, aabb.clone());
+        }
+    }
+
+    /// Returns sorted indices of objects which bounding boxes overlap `aabb`.
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<usize> {
+        self.query_cells(aabb, |entry| entry.aabb.intersects(aabb))
+    }
+
+    /// Returns sorted indices of objects which bounding boxes are not farther than `radius` from `center`.
+    pub fn query_radius(&self, center: &Vector<f32>, radius: f32) -> Vec<usize> {
+        self.query_cells(&Aabb::around(center, radius), |entry| {
+            entry.aabb.dim() == center.dim() && entry.aabb.square_distance(center) <= radius * radius
+        })
+    }
+
+    /// Returns sorted indices of objects in cells overlapping `aabb` which pass `filter`.
+    fn query_cells(&self, aabb: &Aabb, filter: impl Fn(&SpatialHashEntry) -> bool) -> Vec<usize> {
+        let mut result = vec![];
+        SpatialHash::for_each_cell(&self.cell_key(&aabb.min), &self.cell_key(&aabb.max), |key| {
+            if let Some(ids) = self.cells.get(key) {
+                result.extend(ids.iter().filter(|id| filter(&self.entries[id])));
+            }
+        });
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    /// Returns sorted pairs `(a, b)`, `a < b`, of objects which bounding boxes overlap.
+    /// They are candidates for exact collision test.
+    pub fn potential_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        for ids in self.cells.values() {
+            for (k, a) in ids.iter().enumerate() {
+                for b in &ids[k + 1..] {
+                    let (a, b) = (usize::min(*a, *b), usize::max(*a, *b));
+                    if self.entries[&a].aabb.intersects(&self.entries[&b].aabb) {
+                        pairs.push((a, b));
+                    }
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+}
+
+
+#[cfg(test)]
+mod spatial_hash_tests {
+    use super::*;
+
+    fn sphere(x: f32, y: f32, z: f32, r: f32) -> Option<Aabb> {
+        Some(Aabb::around(&Vector::from_xyz(x, y, z), r))
+    }
+
+    fn hash() -> SpatialHash {
+        let mut hash = SpatialHash::new(2.0).unwrap();
+        hash.update_all(&[
+            sphere(0.0, 0.0, 0.0, 0.5),
+            sphere(0.8, 0.0, 0.0, 0.5),
+            sphere(10.0, 0.0, 0.0, 0.5),
+            None,
+            sphere(-5.0, -5.0, 0.0, 3.0),
+        ]);
+        hash
+    }
+
+    #[test]
+    fn new_invalid() {
+        assert!(SpatialHash::new(0.0).is_none());
+        assert!(SpatialHash::new(-1.0).is_none());
+    }
+
+    #[test]
+    fn query_radius() {
+        let hash = hash();
+        assert_eq!(hash.len(), 4);
+        assert_eq!(hash.query_radius(&Vector::zero3(), 1.0), vec![0, 1]);
+        assert_eq!(hash.query_radius(&Vector::from_xyz(9.0, 0.0, 0.0), 0.6), vec![2]);
+        assert_eq!(hash.query_radius(&Vector::from_xyz(9.0, 0.0, 0.0), 0.4), vec![]);
+        assert_eq!(hash.query_radius(&Vector::from_xyz(-2.0, 0.0, 0.0), 2.0), vec![0, 4]);
+    }
+
+    #[test]
+    fn query_aabb() {
+        let hash = hash();
+        let aabb = Aabb::new(Vector::from_xyz(-10.0, -10.0, -1.0), Vector::from_xyz(0.0, 0.0, 1.0)).unwrap();
+        assert_eq!(hash.query_aabb(&aabb), vec![0, 4]);
+    }
+
+    #[test]
+    fn potential_pairs() {
+        let hash = hash();
+        assert_eq!(hash.potential_pairs(), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn moved_objects() {
+        let mut hash = hash();
+        hash.update(2, sphere(1.5, 0.0, 0.0, 0.5));
+        assert_eq!(hash.potential_pairs(), vec![(0, 1), (1, 2)]);
+        assert_eq!(hash.query_radius(&Vector::from_xyz(10.0, 0.0, 0.0), 1.0), vec![]);
+        // Object moved inside of the same cells.
+        hash.update(2, sphere(1.4, 0.0, 0.0, 0.5));
+        assert_eq!(hash.query_radius(&Vector::from_xyz(2.0, 0.0, 0.0), 0.1), vec![]);
+        hash.update_all(&[sphere(0.0, 0.0, 0.0, 0.5)]);
+        assert_eq!(hash.len(), 1);
+        assert_eq!(hash.query_radius(&Vector::from_xyz(1.4, 0.0, 0.0), 0.1), vec![]);
+        assert!(hash.remove(0));
+        assert!(hash.is_empty());
+        assert!(!hash.remove(0));
+    }
+
+    #[test]
+    fn four_dimensional() {
+        let mut hash = SpatialHash::new(1.0).unwrap();
+        hash.update(0, Some(Aabb::around(&Vector::new(vec![0.0, 0.0, 0.0, 0.0]), 0.5)));
+        hash.update(1, Some(Aabb::around(&Vector::new(vec![0.0, 0.0, 0.0, 3.0]), 0.5)));
+        assert_eq!(hash.query_radius(&Vector::new(vec![0.0, 0.0, 0.0, 2.0]), 1.0), vec![1]);
+        assert_eq!(hash.potential_pairs(), vec![]);
+    }
+}
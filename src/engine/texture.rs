@@ -0,0 +1,239 @@
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+use std::fs;
+use std::rc::Rc;
+
+use super::*;
+use crate::utils::{next_netpbm_token, XorShift};
+
+
+/// How texture colour is read between texel centres.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureFilter {
+    /// Colour of the nearest texel, gives crisp pixelated look.
+    #[default]
+    Nearest,
+    /// Colours of four nearest texels blended by distance.
+    Bilinear,
+}
+
+/// Image of `width` × `height` texels stored row by row from the top left corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    /// Creates image from pixels stored row by row.
+    /// Returns `None` if image is empty or number of pixels is not `width * height`.
+    pub fn new(width: usize, height: usize, pixels: Vec<Rgb>) -> Option<Image> {
+        if width == 0 || height == 0 || pixels.len() != width * height {
+            return None;
+        }
+        Some(Image { width, height, pixels })
+    }
+
+    /// Parses PPM image, both plain (`P3`) and binary (`P6`) formats are supported.
+    pub fn from_ppm(bytes: &[u8]) -> Result<Image, Box<dyn std::error::Error>> {
+        let mut position = 0;
+        let magic = next_netpbm_token(bytes, &mut position).ok_or("Missing PPM magic number")?;
+        let width: usize = next_netpbm_token(bytes, &mut position).ok_or("Missing PPM width")?.parse()?;
+        let height: usize = next_netpbm_token(bytes, &mut position).ok_or("Missing PPM height")?.parse()?;
+        let max_value: u32 = next_netpbm_token(bytes, &mut position).ok_or("Missing PPM max value")?.parse()?;
+        if max_value == 0 || max_value > 65535 {
+            return Err("PPM max value must be from 1 to 65535".into());
+        }
+        let count = width.checked_mul(height).and_then(|pixels| pixels.checked_mul(3)).ok_or("PPM image is too large")?;
+
+        let values: Vec<u32> = match magic.as_str() {
+            "P3" => (0..count)
+                .map(|_| next_netpbm_token(bytes, &mut position).ok_or("Not enough PPM pixels"))
+                .map(|token| Ok(token?.parse::<u32>()?))
+                .collect::<Result<Vec<u32>, Box<dyn std::error::Error>>>()?,
+            "P6" => {
+                // Single whitespace separates header from raster.
+                let raster = bytes.get(position + 1..).ok_or("Not enough PPM pixels")?;
+                let bytes_per_value = if max_value < 256 { 1 } else { 2 };
+                if raster.len() / bytes_per_value < count {
+                    return Err("Not enough PPM pixels".into());
+                }
+                raster
+                    .chunks(bytes_per_value)
+                    .take(count)
+                    .map(|chunk| chunk.iter().fold(0, |value, byte| value * 256 + *byte as u32))
+                    .collect()
+            }
+            _ => return Err(format!("Unsupported PPM format {}", magic).into()),
+        };
+
+        let channel = |value: u32| value as f32 / max_value as f32;
+        let pixels = values
+            .chunks(3)
+            .map(|rgb| Rgb::new(channel(rgb[0]), channel(rgb[1]), channel(rgb[2])))
+            .collect();
+        Ok(Image::new(width, height, pixels).ok_or("PPM image is empty")?)
+    }
+
+    /// Loads image from PPM file, see `from_ppm`.
+    pub fn load_ppm(filepath: &str) -> Result<Image, Box<dyn std::error::Error>> {
+        Image::from_ppm(&fs::read(filepath)?)
+    }
+
+    /// Returns texel in `row` and `column`, both wrap around the image.
+    fn texel(&self, row: i64, column: i64) -> Rgb {
+        let i = row.rem_euclid(self.height as i64) as usize;
+        let j = column.rem_euclid(self.width as i64) as usize;
+        self.pixels[i * self.width + j]
+    }
+
+    /// Returns colour at `(u, v)` where `(0, 0)` is the top left corner and `(1, 1)` is the bottom right one.
+    /// Image repeats out of the unit square.
+    pub fn sample(&self, u: f32, v: f32, filter: TextureFilter) -> Rgb {
+        let x = u * self.width as f32;
+        let y = v * self.height as f32;
+        match filter {
+            TextureFilter::Nearest => self.texel(y.floor() as i64, x.floor() as i64),
+            TextureFilter::Bilinear => {
+                // Texel centres are at half-integer coordinates.
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (i, j) = (y.floor() as i64, x.floor() as i64);
+                let (fy, fx) = (y - y.floor(), x - x.floor());
+                let top = self.texel(i, j).lerp(&self.texel(i, j + 1), fx);
+                let bottom = self.texel(i + 1, j).lerp(&self.texel(i + 1, j + 1), fx);
+                top.lerp(&bottom, fy)
+            }
+        }
+    }
+}
+
+/// Colour pattern over texture coordinates `(u, v)` of a surface.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Texture {
+    /// Squares of side `size` of colours `a` and `b`.
+    Checkerboard { a: Rgb, b: Rgb, size: f32 },
+    /// Stripes of `width` along `v` of colours `a` and `b`.
+    Stripes { a: Rgb, b: Rgb, width: f32 },
+    /// Perlin noise with features of size `scale` blending `a` and `b`.
+    Noise { a: Rgb, b: Rgb, scale: f32 },
+    /// Colour changes from `a` at `v = 0` to `b` at `v = 1`.
+    Gradient { a: Rgb, b: Rgb },
+    /// Image stretched over square of side `size` and repeated.
+    Image { image: Rc<Image>, filter: TextureFilter, size: f32 },
+}
+
+impl Texture {
+    /// Returns colour of the texture at `(u, v)`.
+    pub fn sample(&self, (u, v): (f32, f32)) -> Rgb {
+        match self {
+            Texture::Checkerboard { a, b, size } => {
+                let cell = (u / size).floor() as i64 + (v / size).floor() as i64;
+                if cell.rem_euclid(2) == 0 { *a } else { *b }
+            }
+            Texture::Stripes { a, b, width } => {
+                if ((u / width).floor() as i64).rem_euclid(2) == 0 { *a } else { *b }
+            }
+            Texture::Noise { a, b, scale } => {
+                // Perlin noise is within `[-1 / √2, 1 / √2]` in two dimensions.
+                let t = 0.5 + perlin(u / scale, v / scale) * FRAC_1_SQRT_2;
+                a.lerp(b, t.clamp(0.0, 1.0))
+            }
+            Texture::Gradient { a, b } => a.lerp(b, v.clamp(0.0, 1.0)),
+            Texture::Image { image, filter, size } => image.sample(u / size, v / size, *filter),
+        }
+    }
+}
+
+/// Returns two-dimensional Perlin gradient noise at `(x, y)`, it is zero in integer points.
+fn perlin(x: f32, y: f32) -> f32 {
+    // Random unit gradient in lattice point, the same for the same point.
+    let gradient = |i: i64, j: i64| {
+        let angle = 2.0 * PI * XorShift::new(((i as u64) << 32) ^ (j as u32 as u64)).next_f32();
+        (angle.cos(), angle.sin())
+    };
+    let dot = |i: i64, j: i64| {
+        let (gx, gy) = gradient(i, j);
+        gx * (x - i as f32) + gy * (y - j as f32)
+    };
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let (i, j) = (x.floor() as i64, y.floor() as i64);
+    let (sx, sy) = (fade(x - i as f32), fade(y - j as f32));
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    lerp(
+        lerp(dot(i, j), dot(i + 1, j), sx),
+        lerp(dot(i, j + 1), dot(i + 1, j + 1), sx),
+        sy,
+    )
+}
+
+
+#[cfg(test)]
+mod texture_tests {
+    use super::*;
+
+    #[test]
+    fn procedural_textures() {
+        let (black, white) = (Rgb::BLACK, Rgb::WHITE);
+        let checkerboard = Texture::Checkerboard { a: black, b: white, size: 0.5 };
+        assert_eq!(checkerboard.sample((0.1, 0.1)), black);
+        assert_eq!(checkerboard.sample((0.6, 0.1)), white);
+        assert_eq!(checkerboard.sample((0.6, 0.6)), black);
+        assert_eq!(checkerboard.sample((-0.1, 0.1)), white);
+
+        let stripes = Texture::Stripes { a: black, b: white, width: 0.25 };
+        assert_eq!(stripes.sample((0.1, 0.9)), black);
+        assert_eq!(stripes.sample((0.3, 0.0)), white);
+
+        let gradient = Texture::Gradient { a: black, b: white };
+        assert_eq!(gradient.sample((0.3, 0.25)), Rgb::grey(0.25));
+        assert_eq!(gradient.sample((0.3, 2.0)), white);
+
+        let noise = Texture::Noise { a: black, b: white, scale: 0.25 };
+        // Noise is zero in lattice points and changes between them.
+        assert_eq!(noise.sample((0.5, 0.25)), Rgb::grey(0.5));
+        let values: Vec<f32> = (0..20).map(|k| noise.sample((0.013 * k as f32, 0.37)).r).collect();
+        assert!(values.iter().all(|value| (0.0..=1.0).contains(value)));
+        assert!(values.iter().any(|value| (value - 0.5).abs() > 0.05));
+        assert_eq!(noise.sample((0.17, 0.37)), noise.sample((0.17, 0.37)));
+    }
+
+    #[test]
+    fn plain_ppm() {
+        let ppm = b"P3\n# comment\n2 1\n4\n4 0 0  0 2 4\n";
+        let image = Image::from_ppm(ppm).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.pixels, vec![Rgb::new(1.0, 0.0, 0.0), Rgb::new(0.0, 0.5, 1.0)]);
+    }
+
+    #[test]
+    fn binary_ppm() {
+        let mut ppm = b"P6 1 2 255\n".to_vec();
+        ppm.extend([255, 255, 255, 0, 51, 0]);
+        let image = Image::from_ppm(&ppm).unwrap();
+        assert_eq!(image.pixels, vec![Rgb::WHITE, Rgb::new(0.0, 0.2, 0.0)]);
+
+        ppm.pop();
+        assert!(Image::from_ppm(&ppm).is_err());
+        assert!(Image::from_ppm(b"P5 1 1 255\n\0").is_err());
+        assert!(Image::from_ppm(b"P6 9223372036854775807 3 255\n\0").is_err());
+        assert!(Image::from_ppm(b"P3 6148914691236517206 1 255\n0").is_err());
+    }
+
+    #[test]
+    fn filtering() {
+        let image = Image::new(2, 1, vec![Rgb::BLACK, Rgb::WHITE]).unwrap();
+        assert_eq!(image.sample(0.4, 0.5, TextureFilter::Nearest), Rgb::BLACK);
+        assert_eq!(image.sample(0.6, 0.5, TextureFilter::Nearest), Rgb::WHITE);
+        // Image repeats.
+        assert_eq!(image.sample(1.6, -0.5, TextureFilter::Nearest), Rgb::WHITE);
+
+        // Halfway between texel centres.
+        assert_eq!(image.sample(0.5, 0.5, TextureFilter::Bilinear), Rgb::grey(0.5));
+        assert_eq!(image.sample(0.25, 0.5, TextureFilter::Bilinear), Rgb::BLACK);
+        // Left texel of the repeated image is next to the right one.
+        assert_eq!(image.sample(0.0, 0.5, TextureFilter::Bilinear), Rgb::grey(0.5));
+
+        let texture = Texture::Image { image: Rc::new(image), filter: TextureFilter::Nearest, size: 2.0 };
+        assert_eq!(texture.sample((1.2, 0.0)), Rgb::WHITE);
+    }
+}
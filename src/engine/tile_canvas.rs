@@ -0,0 +1,141 @@
+use super::*;
+
+
+/// Vertical slice of wall seen in one screen column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallSlice {
+    pub hit: TileHit,
+    /// First row of the slice.
+    pub top: usize,
+    /// Row after the last row of the slice.
+    pub bottom: usize,
+}
+
+/// Screen of the 2.5d render mode. Every column is one ray cast through `TileMap`:
+/// wall slice is drawn in the middle of column, ceiling above it and floor below it.
+pub struct TileCanvas {
+    pub width: usize,
+    pub height: usize,
+    pub columns: Vec<Option<WallSlice>>,
+}
+
+impl TileCanvas {
+    pub fn new(width: usize, height: usize) -> TileCanvas {
+        TileCanvas {
+            width,
+            height,
+            columns: vec![None; width],
+        }
+    }
+
+    pub fn new_from_game_config(config: &GameConfig) -> TileCanvas {
+        TileCanvas::new(config.screen_width, config.screen_height)
+    }
+
+    /// Casts one ray per column from camera position projected on xy plane.
+    /// Only the horizontal part of the camera view direction is used,
+    /// z coordinate of the camera is the eye height above the floor.
+    pub fn update(&mut self, camera: &Camera, tile_map: &TileMap) {
+        self.columns = vec![None; self.width];
+        let position = &camera.frame.initial_point;
+        let view = &camera.frame.base[0];
+        let length = (view[0] * view[0] + view[1] * view[1]).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        let forward = (view[0] / length, view[1] / length);
+        let left = (-forward.1, forward.0);
+        let half_width = (0.5 * camera.horizontal_fov).tan();
+        // Rows per unit of height at distance 1.
+        let scale = self.height as f32 / (2.0 * (0.5 * camera.vertical_fov).tan());
+        let horizon = 0.5 * self.height as f32;
+        let eye = position[2];
+
+        for j in 0..self.width {
+            let u = (1.0 - 2.0 * (j as f32 + 0.5) / self.width as f32) * half_width;
+            let direction = (forward.0 + u * left.0, forward.1 + u * left.1);
+            let hit = match tile_map.cast_ray((position[0], position[1]), direction) {
+                None => continue,
+                Some(hit) => hit,
+            };
+            // Slice height is inversely proportional to the distance.
+            let distance = f32::max(hit.distance, f32::EPSILON);
+            let top = horizon - (1.0 - eye) * scale / distance;
+            let bottom = horizon + eye * scale / distance;
+            self.columns[j] = Some(WallSlice {
+                hit,
+                top: top.round().clamp(0.0, self.height as f32) as usize,
+                bottom: bottom.round().clamp(0.0, self.height as f32) as usize,
+            });
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tile_canvas_tests {
+    use super::*;
+    use crate::math::*;
+
+    fn camera(x: f32, y: f32) -> Camera {
+        let config = GameConfig {
+            camera_fov: std::f32::consts::PI / 2.0,
+            screen_width: 20,
+            screen_height: 20,
+            ..GameConfig::default()
+        };
+        let frame = CoordinateSystem {
+            initial_point: Vector::from_xyz(x, y, 0.5),
+            ..CoordinateSystem::default3()
+        };
+        let mut camera = Camera::new_in_frame(frame, &config).unwrap();
+        camera.vertical_fov = std::f32::consts::PI / 2.0;
+        camera
+    }
+
+    const MAP: &str = "\
+#######
+#.....#
+#.....#
+#.....#
+#######
+";
+
+    #[test]
+    fn centered_slices() {
+        let map = TileMap::from_ascii(MAP).unwrap();
+        let mut canvas = TileCanvas::new(20, 20);
+        canvas.update(&camera(4.0, 2.5), &map);
+        // Wall at distance 2 in front of camera with vertical fov of 90 degrees.
+        let slice = canvas.columns[10].unwrap();
+        assert_eq!(slice.hit.tile, (6, 2));
+        assert_eq!((slice.top, slice.bottom), (8, 13));
+        assert_eq!(canvas.columns[9].unwrap().hit.distance, 2.0);
+    }
+
+    #[test]
+    fn nearer_walls_are_taller() {
+        let map = TileMap::from_ascii(MAP).unwrap();
+        let mut near = TileCanvas::new(20, 20);
+        near.update(&camera(5.5, 2.5), &map);
+        let mut far = TileCanvas::new(20, 20);
+        far.update(&camera(2.5, 2.5), &map);
+        let height = |canvas: &TileCanvas| {
+            let slice = canvas.columns[10].unwrap();
+            slice.bottom - slice.top
+        };
+        assert!(height(&near) > height(&far));
+        assert_eq!(height(&near), 20);
+    }
+
+    #[test]
+    fn no_fish_eye() {
+        let map = TileMap::from_ascii(MAP).unwrap();
+        let mut canvas = TileCanvas::new(20, 20);
+        canvas.update(&camera(4.5, 2.5), &map);
+        // Flat wall in front of camera has the same distance in every column.
+        for slice in &canvas.columns {
+            assert!((slice.unwrap().hit.distance - 1.5).abs() < 1e-5);
+        }
+    }
+}
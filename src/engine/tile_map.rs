@@ -0,0 +1,302 @@
+use console_engine::Color;
+
+
+/// Cell of `TileMap`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tile {
+    Empty,
+    /// Wall drawn with the given character.
+    Wall(char),
+    /// Door blocks rays only while it is closed.
+    Door { open: bool },
+}
+
+impl Tile {
+    /// Parses map character: `' '` and `'.'` are empty tiles, `'D'` is closed door,
+    /// `'d'` is open door, any other character is wall.
+    pub fn from_char(c: char) -> Tile {
+        match c {
+            ' ' | '.' => Tile::Empty,
+            'D' => Tile::Door { open: false },
+            'd' => Tile::Door { open: true },
+            c => Tile::Wall(c),
+        }
+    }
+
+    /// Returns true if tile stops rays and movement.
+    pub fn is_solid(&self) -> bool {
+        match self {
+            Tile::Empty => false,
+            Tile::Wall(_) => true,
+            Tile::Door { open } => !open,
+        }
+    }
+
+    /// Character to draw the tile with.
+    pub fn glyph(&self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall(c) => *c,
+            Tile::Door { .. } => 'D',
+        }
+    }
+}
+
+/// Result of casting a ray through `TileMap`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileHit {
+    /// Ray parameter of the hit.
+    pub distance: f32,
+    /// Coordinates `(x, y)` of the hit tile.
+    pub tile: (usize, usize),
+    /// Axis of the crossed tile border: 0 if ray hit a wall facing x, 1 if facing y.
+    pub side: usize,
+    pub kind: Tile,
+}
+
+/// 2d grid of tiles for Wolfenstein-like rendering.
+///
+/// Tile `(x, y)` is the unit square from `(x, y)` to `(x + 1, y + 1)` of xy plane,
+/// walls stand from `z = 0` to `z = 1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileMap {
+    width: usize,
+    height: usize,
+    /// Rows of tiles, `tiles[y][x]`.
+    tiles: Vec<Vec<Tile>>,
+    pub floor_color: Color,
+    pub ceiling_color: Color,
+    /// Center of the tile marked with `'@'` in map file.
+    pub spawn: Option<(f32, f32)>,
+}
+
+impl TileMap {
+    /// Creates map of empty tiles.
+    pub fn new(width: usize, height: usize) -> TileMap {
+        TileMap {
+            width,
+            height,
+            tiles: vec![vec![Tile::Empty; width]; height],
+            floor_color: Color::DarkGrey,
+            ceiling_color: Color::Black,
+            spawn: None,
+        }
+    }
+
+    /// Parses map from text. Optional header lines `floor: r g b` and `ceiling: r g b` set colours,
+    /// other lines are rows of tiles (see `Tile::from_char`), `'@'` marks empty spawn tile.
+    /// The first row is the north edge of the map, i.e. it has the largest y,
+    /// so the map looks from above the same as in the file.
+    pub fn from_ascii(text: &str) -> Result<TileMap, Box<dyn std::error::Error>> {
+        let mut floor_color = None;
+        let mut ceiling_color = None;
+        let mut rows: Vec<&str> = vec![];
+        for line in text.lines() {
+            if let Some(rgb) = line.strip_prefix("floor:") {
+                floor_color = Some(parse_color(rgb)?);
+            } else if let Some(rgb) = line.strip_prefix("ceiling:") {
+                ceiling_color = Some(parse_color(rgb)?);
+            } else if !line.trim().is_empty() {
+                rows.push(line);
+            }
+        }
+        if rows.is_empty() {
+            return Err("Map has no tiles".into());
+        }
+
+        let width = rows.iter().map(|row| row.chars().count()).max().unwrap();
+        let mut map = TileMap::new(width, rows.len());
+        for (row_index, row) in rows.iter().enumerate() {
+            let y = rows.len() - 1 - row_index;
+            for (x, c) in row.chars().enumerate() {
+                if c == '@' {
+                    map.spawn = Some((x as f32 + 0.5, y as f32 + 0.5));
+                    continue;
+                }
+                map.tiles[y][x] = Tile::from_char(c);
+            }
+        }
+        map.floor_color = floor_color.unwrap_or(map.floor_color);
+        map.ceiling_color = ceiling_color.unwrap_or(map.ceiling_color);
+        Ok(map)
+    }
+
+    /// Loads map from ASCII file, see `TileMap::from_ascii`.
+    pub fn load_from_file(filepath: &str) -> Result<TileMap, Box<dyn std::error::Error>> {
+        TileMap::from_ascii(&std::fs::read_to_string(filepath)?)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns tile or `None` if coordinates are out of the map.
+    pub fn get_tile(&self, x: usize, y: usize) -> Option<Tile> {
+        self.tiles.get(y)?.get(x).copied()
+    }
+
+    /// Sets tile. If coordinates are out of the map returns error.
+    pub fn set_tile(&mut self, x: usize, y: usize, tile: Tile) -> Result<(), ()> {
+        let cell = self.tiles.get_mut(y).ok_or(())?.get_mut(x).ok_or(())?;
+        *cell = tile;
+        Ok(())
+    }
+
+    /// Opens closed door and closes open one. If tile is not a door returns error.
+    pub fn toggle_door(&mut self, x: usize, y: usize) -> Result<(), ()> {
+        match self.get_tile(x, y) {
+            Some(Tile::Door { open }) => self.set_tile(x, y, Tile::Door { open: !open }),
+            _ => Err(()),
+        }
+    }
+
+    /// Returns tile which contains point `(x, y)` or `None` if point is out of the map.
+    pub fn tile_at(&self, x: f32, y: f32) -> Option<Tile> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        self.get_tile(x as usize, y as usize)
+    }
+
+    /// Returns true if point `(x, y)` is out of the map or inside of a solid tile.
+    pub fn is_blocked(&self, x: f32, y: f32) -> bool {
+        self.tile_at(x, y).is_none_or(|tile| tile.is_solid())
+    }
+
+    /// Casts ray with DDA through tiles and returns the first solid tile.
+    /// Ray which leaves the map hits nothing.
+    ///
+    /// If `direction` is `forward + u * left` for unit `forward`,
+    /// the distance of the hit is perpendicular distance to the camera plane, so walls are not fish-eyed.
+    pub fn cast_ray(&self, origin: (f32, f32), direction: (f32, f32)) -> Option<TileHit> {
+        let origin = [origin.0, origin.1];
+        let direction = [direction.0, direction.1];
+        let tile = self.tile_at(origin[0], origin[1])?;
+        let mut cell = [origin[0] as usize, origin[1] as usize];
+        if tile.is_solid() {
+            return Some(TileHit { distance: 0.0, tile: (cell[0], cell[1]), side: 0, kind: tile });
+        }
+
+        let mut step = [0isize; 2];
+        let mut t_max = [f32::INFINITY; 2];
+        let mut t_delta = [f32::INFINITY; 2];
+        for axis in 0..2 {
+            if direction[axis] > 0.0 {
+                step[axis] = 1;
+                t_max[axis] = (cell[axis] as f32 + 1.0 - origin[axis]) / direction[axis];
+            } else if direction[axis] < 0.0 {
+                step[axis] = -1;
+                t_max[axis] = (cell[axis] as f32 - origin[axis]) / direction[axis];
+            }
+            if direction[axis] != 0.0 {
+                t_delta[axis] = 1.0 / direction[axis].abs();
+            }
+        }
+        if step == [0, 0] {
+            return None;
+        }
+
+        loop {
+            let side = if t_max[0] < t_max[1] { 0 } else { 1 };
+            let distance = t_max[side];
+            cell[side] = cell[side].checked_add_signed(step[side])?;
+            t_max[side] += t_delta[side];
+            let tile = self.get_tile(cell[0], cell[1])?;
+            if tile.is_solid() {
+                return Some(TileHit { distance, tile: (cell[0], cell[1]), side, kind: tile });
+            }
+        }
+    }
+}
+
+/// Parses colour from `"r g b"`.
+fn parse_color(text: &str) -> Result<Color, Box<dyn std::error::Error>> {
+    let channels = text
+        .split_whitespace()
+        .map(|channel| channel.parse::<u8>())
+        .collect::<Result<Vec<u8>, _>>()?;
+    match channels[..] {
+        [r, g, b] => Ok(Color::Rgb { r, g, b }),
+        _ => Err(format!("Expected three colour channels, got `{}`", text.trim()).into()),
+    }
+}
+
+
+#[cfg(test)]
+mod tile_map_tests {
+    use super::*;
+
+    const MAP: &str = "\
+floor: 10 20 30
+#####
+#@..#
+#.D.#
+#####
+";
+
+    #[test]
+    fn from_ascii() {
+        let map = TileMap::from_ascii(MAP).unwrap();
+        assert_eq!((map.width(), map.height()), (5, 4));
+        assert_eq!(map.floor_color, Color::Rgb { r: 10, g: 20, b: 30 });
+        assert_eq!(map.ceiling_color, Color::Black);
+        assert_eq!(map.spawn, Some((1.5, 2.5)));
+        assert_eq!(map.get_tile(1, 2), Some(Tile::Empty));
+        assert_eq!(map.get_tile(2, 1), Some(Tile::Door { open: false }));
+        assert_eq!(map.get_tile(0, 0), Some(Tile::Wall('#')));
+        assert_eq!(map.get_tile(5, 0), None);
+    }
+
+    #[test]
+    fn from_ascii_bad_color() {
+        assert!(TileMap::from_ascii("ceiling: 1 2\n#").is_err());
+        assert!(TileMap::from_ascii("floor: 1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn cast_ray_straight() {
+        let map = TileMap::from_ascii(MAP).unwrap();
+        let hit = map.cast_ray((1.5, 2.5), (1.0, 0.0)).unwrap();
+        assert_eq!(hit, TileHit { distance: 2.5, tile: (4, 2), side: 0, kind: Tile::Wall('#') });
+        let hit = map.cast_ray((1.5, 2.5), (0.0, 1.0)).unwrap();
+        assert_eq!((hit.distance, hit.tile, hit.side), (0.5, (1, 3), 1));
+    }
+
+    #[test]
+    fn cast_ray_door() {
+        let mut map = TileMap::from_ascii(MAP).unwrap();
+        let hit = map.cast_ray((2.5, 2.5), (0.0, -1.0)).unwrap();
+        assert_eq!((hit.distance, hit.kind), (0.5, Tile::Door { open: false }));
+        map.toggle_door(2, 1).unwrap();
+        let hit = map.cast_ray((2.5, 2.5), (0.0, -1.0)).unwrap();
+        assert_eq!((hit.distance, hit.tile), (1.5, (2, 0)));
+        assert!(map.toggle_door(0, 0).is_err());
+    }
+
+    #[test]
+    fn cast_ray_diagonal() {
+        let map = TileMap::from_ascii(MAP).unwrap();
+        let hit = map.cast_ray((1.5, 1.5), (1.0, 1.0)).unwrap();
+        assert_eq!(hit.tile, (2, 3));
+        assert_eq!(hit.distance, 1.5);
+    }
+
+    #[test]
+    fn cast_ray_leaves_map() {
+        let map = TileMap::from_ascii("...\n...").unwrap();
+        assert!(map.cast_ray((0.5, 0.5), (1.0, 0.3)).is_none());
+        assert!(map.cast_ray((0.5, 0.5), (-1.0, 0.0)).is_none());
+        assert!(map.cast_ray((-1.0, 0.5), (1.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn load_from_file() {
+        let map = TileMap::load_from_file("maps/level1.map").expect("Could not read maps/level1.map");
+        assert!(map.spawn.is_some());
+        assert!(TileMap::load_from_file("maps/missing.map").is_err());
+    }
+}
@@ -5,20 +5,22 @@ use crate::math::*;
 pub struct Transform {
     pub position: Vector<f32>,
     direction: Vector<f32>,
+    /// Uniform scale of the object around its position.
+    pub scale: f32,
 }
 
 impl Default for Transform {
     fn default() -> Self {
-        Transform { position: Vector::zero3(), direction: Transform::default_direction() }
+        Transform { position: Vector::zero3(), direction: Transform::default_direction(), scale: 1.0 }
     }
 }
 
 impl Transform {
     /// Creates transform from position and direction vectors.
-    /// Those vectors must have dimenstion of 3.
+    /// Those vectors must have the same non-zero dimension.
     /// Direction vector will be normalized.
     pub fn new(position: Vector<f32>, direction: &Vector<f32>) -> Option<Transform> {
-        if position.dim() != 3 || direction.dim() != 3 {
+        if position.dim() == 0 || position.dim() != direction.dim() {
             return None;
         }
         
@@ -41,6 +43,50 @@ impl Transform {
         Transform::new(position, &direction)
     }
 
+    /// Creates transform of dimension `dim` in the origin looking along the first axis.
+    /// If `dim` is zero returns `None`.
+    pub fn default_nd(dim: usize) -> Option<Transform> {
+        Transform::new(Vector::zero(dim), &Vector::unit(dim, 0))
+    }
+
+    /// Returns dimension of the space where transform lives.
+    pub fn dim(&self) -> usize {
+        self.position.dim()
+    }
+
+    /// Returns ray in coordinates relative to the transform position and scale.
+    /// Ray parameter of any point stays the same.
+    /// If dimensions of ray and transform differ returns `None`.
+    pub fn to_local_ray(&self, ray: &Ray) -> Option<Ray> {
+        if ray.direction.dim() != self.dim() {
+            return None;
+        }
+        Some(Ray::new(
+            &(&ray.point - &self.position)? / self.scale,
+            &ray.direction / self.scale,
+        ))
+    }
+
+    /// Returns point in coordinates relative to the transform position and scale.
+    /// If dimensions of point and transform differ returns `None`.
+    pub fn to_local_point(&self, point: &Vector<f32>) -> Option<Vector<f32>> {
+        Some(&(point - &self.position)? / self.scale)
+    }
+
+    /// Returns box in world coordinates which contains `local_box` given relative to the transform.
+    /// If dimensions of box and transform differ returns `None`.
+    pub fn to_world_box(&self, local_box: &Aabb) -> Option<Aabb> {
+        if local_box.dim() != self.dim() {
+            return None;
+        }
+        let a = (&(&local_box.min * self.scale) + &self.position)?;
+        let b = (&(&local_box.max * self.scale) + &self.position)?;
+        Aabb::new(
+            Vector::new((0..a.dim()).map(|i| f32::min(a[i], b[i])).collect()),
+            Vector::new((0..a.dim()).map(|i| f32::max(a[i], b[i])).collect()),
+        )
+    }
+
     pub fn default_direction() -> Vector<f32> {
         Vector::from_xyz(1.0, 0.0, 0.0)
     }
@@ -60,4 +106,45 @@ impl Transform {
         };
         return Ok(())
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod transform_tests {
+    use super::*;
+
+    #[test]
+    fn new4d() {
+        let t = Transform::new(Vector::zero(4), &Vector::new(vec![0.0, 0.0, 0.0, 2.0])).unwrap();
+        assert_eq!(t.dim(), 4);
+        assert_eq!(t.get_direction(), &Vector::new(vec![0.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn new_different_dimensions() {
+        assert!(Transform::new(Vector::zero(4), &Vector::from_xyz(1.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn new_zero_direction() {
+        assert!(Transform::new(Vector::zero(5), &Vector::zero(5)).is_none());
+    }
+
+    #[test]
+    fn default_nd() {
+        let t = Transform::default_nd(2).unwrap();
+        assert_eq!(t.position, Vector::zero(2));
+        assert_eq!(t.get_direction(), &Vector::from_xy(1.0, 0.0));
+        assert!(Transform::default_nd(0).is_none());
+    }
+
+    #[test]
+    fn to_local_ray_keeps_parameter() {
+        let mut t = Transform::new_from_coords(1.0, 2.0, 3.0, 1.0, 0.0, 0.0).unwrap();
+        t.scale = 2.0;
+        let ray = Ray::new(Vector::from_xyz(1.0, 2.0, 5.0), Vector::from_xyz(0.0, 0.0, 4.0));
+        let local = t.to_local_ray(&ray).unwrap();
+        assert_eq!(local.point, Vector::from_xyz(0.0, 0.0, 1.0));
+        assert_eq!(local.direction, Vector::from_xyz(0.0, 0.0, 2.0));
+    }
+}
@@ -0,0 +1,144 @@
+use super::*;
+
+
+/// Rectangle of the console in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    /// Column of the left side.
+    pub x: usize,
+    /// Row of the top side.
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    /// Returns rectangle of the whole screen.
+    pub fn screen(config: &GameConfig) -> Rect {
+        Rect::new(0, 0, config.screen_width, config.screen_height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// Camera which renders the world into a rectangle of the console.
+/// Every viewport has its own canvases sized to its rectangle.
+pub struct Viewport {
+    pub camera: Camera,
+    pub rect: Rect,
+    pub canvas: Canvas,
+    pub tile_canvas: TileCanvas,
+}
+
+impl Viewport {
+    /// Creates viewport and fits `camera` to the shape of `rect`.
+    pub fn new(camera: Camera, rect: Rect, config: &GameConfig) -> Viewport {
+        let mut viewport = Viewport {
+            camera,
+            rect,
+            canvas: Canvas::new(0, 0),
+            tile_canvas: TileCanvas::new(0, 0),
+        };
+        viewport.set_rect(rect, config);
+        viewport
+    }
+
+    /// Moves viewport to `rect`. Canvases are recreated with the new size
+    /// and camera keeps its horizontal field of view.
+    pub fn set_rect(&mut self, rect: Rect, config: &GameConfig) {
+        self.rect = rect;
+        self.canvas = Canvas::new_for_cells(rect.width, rect.height, config);
+        self.tile_canvas = TileCanvas::new(rect.width, rect.height);
+        if !rect.is_empty() {
+            self.camera.set_aspect_ratio(rect.width as f32 * config.cell_aspect_ratio / rect.height as f32);
+        }
+    }
+}
+
+/// Arrangement of viewports on the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// Viewports keep rectangles given to them, e.g. for picture-in-picture.
+    /// Later viewports are drawn over earlier ones.
+    Manual,
+    /// Screen is split into columns of equal width from the left to the right.
+    #[default]
+    Columns,
+    /// Screen is split into rows of equal height from the top to the bottom.
+    Rows,
+    /// Screen is split into grid of nearly square shape filled row by row.
+    Grid,
+}
+
+impl Layout {
+    /// Returns rectangles of `count` viewports on the screen of `width` × `height` cells.
+    /// Returns `None` for `Layout::Manual`.
+    pub fn rects(&self, width: usize, height: usize, count: usize) -> Option<Vec<Rect>> {
+        let (columns, rows) = match self {
+            Layout::Manual => return None,
+            Layout::Columns => (count, 1),
+            Layout::Rows => (1, count),
+            Layout::Grid => {
+                let columns = (1..=count).find(|columns| columns * columns >= count).unwrap_or(0);
+                (columns, if columns == 0 { 0 } else { count.div_ceil(columns) })
+            }
+        };
+        // Borders of cells are spread evenly, so sizes differ by one cell at most.
+        let border = |k: usize, cells: usize, size: usize| k * size / cells.max(1);
+        let rects = (0..count)
+            .map(|k| {
+                let (row, column) = (k / columns, k % columns);
+                let (x, y) = (border(column, columns, width), border(row, rows, height));
+                Rect::new(x, y, border(column + 1, columns, width) - x, border(row + 1, rows, height) - y)
+            })
+            .collect();
+        Some(rects)
+    }
+}
+
+
+#[cfg(test)]
+mod viewport_tests {
+    use super::*;
+
+    #[test]
+    fn layouts() {
+        assert_eq!(
+            Layout::Columns.rects(11, 4, 2),
+            Some(vec![Rect::new(0, 0, 5, 4), Rect::new(5, 0, 6, 4)])
+        );
+        assert_eq!(
+            Layout::Rows.rects(10, 9, 3),
+            Some(vec![Rect::new(0, 0, 10, 3), Rect::new(0, 3, 10, 3), Rect::new(0, 6, 10, 3)])
+        );
+        assert_eq!(
+            Layout::Grid.rects(10, 4, 3),
+            Some(vec![Rect::new(0, 0, 5, 2), Rect::new(5, 0, 5, 2), Rect::new(0, 2, 5, 2)])
+        );
+        assert_eq!(Layout::Grid.rects(10, 4, 0), Some(vec![]));
+        assert_eq!(Layout::Manual.rects(10, 4, 2), None);
+    }
+
+    #[test]
+    fn viewport_fits_camera() {
+        let config = GameConfig {
+            camera_fov: std::f32::consts::PI / 2.0,
+            render_mode: RenderMode::HalfBlock,
+            cell_aspect_ratio: 0.5,
+            ..GameConfig::default()
+        };
+        let camera = Camera::new(Transform::default(), &config);
+        // Square viewport of 20×10 cells.
+        let viewport = Viewport::new(camera, Rect::new(3, 4, 20, 10), &config);
+        assert_eq!((viewport.canvas.width, viewport.canvas.height), (20, 20));
+        assert_eq!((viewport.tile_canvas.width, viewport.tile_canvas.height), (20, 10));
+        assert!((viewport.camera.vertical_fov - std::f32::consts::PI / 2.0).abs() < 1e-6);
+        assert!((viewport.camera.aspect_ratio - 1.0).abs() < 1e-6);
+    }
+}
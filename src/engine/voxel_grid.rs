@@ -0,0 +1,309 @@
+use super::*;
+use crate::math::*;
+
+
+/// Material id of empty voxel.
+pub const EMPTY_VOXEL: u8 = 0;
+
+/// Result of casting a ray into `VoxelGrid`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelHit {
+    /// Ray parameter of the hit.
+    pub distance: f32,
+    /// Coordinates of the hit voxel.
+    pub voxel: [usize; 3],
+    /// Outward normal of the hit face of voxel. Exactly one component is non-zero.
+    pub normal: [i32; 3],
+    /// Material id of the hit voxel.
+    pub material: u8,
+}
+
+/// Dense 3d grid of voxels. Each voxel stores material id, `EMPTY_VOXEL` means no voxel.
+///
+/// Voxel `(x, y, z)` is the unit cube from `(x, y, z)` to `(x + 1, y + 1, z + 1)` in local coordinates.
+/// Grid is placed at `transform.position` and scaled by `transform.scale`.
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    size: [usize; 3],
+    voxels: Vec<u8>,
+}
+
+impl VoxelGrid {
+    /// Creates empty grid of the given size.
+    pub fn new(size_x: usize, size_y: usize, size_z: usize) -> VoxelGrid {
+        VoxelGrid {
+            size: [size_x, size_y, size_z],
+            voxels: vec![EMPTY_VOXEL; size_x * size_y * size_z],
+        }
+    }
+
+    pub fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> Option<usize> {
+        if x >= self.size[0] || y >= self.size[1] || z >= self.size[2] {
+            return None;
+        }
+        Some((z * self.size[1] + y) * self.size[0] + x)
+    }
+
+    /// Returns material id of voxel or `None` if coordinates are out of the grid.
+    pub fn get_voxel(&self, x: usize, y: usize, z: usize) -> Option<u8> {
+        Some(self.voxels[self.index(x, y, z)?])
+    }
+
+    /// Sets material id of voxel. Use `EMPTY_VOXEL` to remove voxel.
+    /// If coordinates are out of the grid returns error.
+    pub fn set_voxel(&mut self, x: usize, y: usize, z: usize, material: u8) -> Result<(), ()> {
+        let index = self.index(x, y, z).ok_or(())?;
+        self.voxels[index] = material;
+        Ok(())
+    }
+
+    /// Fills box of voxels from `min` to `max` inclusive. Voxels out of the grid are skipped.
+    pub fn fill(&mut self, min: [usize; 3], max: [usize; 3], material: u8) {
+        for z in min[2]..=max[2] {
+            for y in min[1]..=max[1] {
+                for x in min[0]..=max[0] {
+                    let _ = self.set_voxel(x, y, z, material);
+                }
+            }
+        }
+    }
+
+    /// Returns true if voxel with signed coordinates exists and is not empty.
+    fn is_solid(&self, voxel: [i64; 3]) -> bool {
+        if voxel.iter().any(|x| *x < 0) {
+            return false;
+        }
+        self.get_voxel(voxel[0] as usize, voxel[1] as usize, voxel[2] as usize)
+            .is_some_and(|material| material != EMPTY_VOXEL)
+    }
+
+    /// Casts ray through the grid with Amanatides–Woo voxel traversal
+    /// and returns the first non-empty voxel which is not behind the ray point.
+    /// Grid of zero size along any axis has no voxels and is never hit.
+    ///
+    /// http://www.cse.yorku.ca/~amana/research/grid.pdf
+    pub fn raycast(&self, transform: &Transform, ray: &Ray) -> Option<VoxelHit> {
+        if ray.point.dim() != 3 || ray.direction.dim() != 3 || transform.dim() != 3 || self.voxels.is_empty() {
+            return None;
+        }
+        let ray = transform.to_local_ray(ray)?;
+        let origin = [ray.point[0], ray.point[1], ray.point[2]];
+        let direction = [ray.direction[0], ray.direction[1], ray.direction[2]];
+
+        // Clip ray with the bounding box of grid.
+        let mut enter = f32::NEG_INFINITY;
+        let mut exit = f32::INFINITY;
+        let mut enter_axis = None;
+        for axis in 0..3 {
+            let max = self.size[axis] as f32;
+            if direction[axis] == 0.0 {
+                if origin[axis] < 0.0 || origin[axis] > max {
+                    return None;
+                }
+                continue;
+            }
+            let t1 = (0.0 - origin[axis]) / direction[axis];
+            let t2 = (max - origin[axis]) / direction[axis];
+            if f32::min(t1, t2) > enter {
+                enter = f32::min(t1, t2);
+                enter_axis = Some(axis);
+            }
+            exit = f32::min(exit, f32::max(t1, t2));
+        }
+        if enter > exit || exit < 0.0 {
+            return None;
+        }
+
+        let step = direction.map(|d| if d > 0.0 { 1 } else if d < 0.0 { -1 } else { 0 });
+        // Axis of the last crossed voxel border, it gives the normal of the hit face.
+        let mut axis = match enter_axis {
+            Some(axis) if enter >= 0.0 => axis,
+            // Ray starts inside of the grid, so the face is the one ray looks through.
+            _ => (0..3).max_by(|a, b| direction[*a].abs().total_cmp(&direction[*b].abs())).unwrap(),
+        };
+        let start = f32::max(enter, 0.0);
+        let mut voxel = [0i64; 3];
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+        for a in 0..3 {
+            let x = origin[a] + start * direction[a];
+            // Voxel on the border is the one ray goes into.
+            let cell = if step[a] < 0 { x.ceil() - 1.0 } else { x.floor() };
+            voxel[a] = (cell as i64).clamp(0, self.size[a] as i64 - 1);
+            if step[a] != 0 {
+                let border = voxel[a] + if step[a] > 0 { 1 } else { 0 };
+                t_max[a] = (border as f32 - origin[a]) / direction[a];
+                t_delta[a] = 1.0 / direction[a].abs();
+            }
+        }
+
+        let mut t = start;
+        loop {
+            if self.is_solid(voxel) {
+                let mut normal = [0; 3];
+                normal[axis] = -step[axis];
+                let voxel = voxel.map(|x| x as usize);
+                return Some(VoxelHit {
+                    distance: t,
+                    voxel,
+                    normal,
+                    material: self.get_voxel(voxel[0], voxel[1], voxel[2]).unwrap(),
+                });
+            }
+            axis = (0..3).min_by(|a, b| t_max[*a].total_cmp(&t_max[*b])).unwrap();
+            t = t_max[axis];
+            if t > exit {
+                return None;
+            }
+            voxel[axis] += step[axis] as i64;
+            if voxel[axis] < 0 || voxel[axis] >= self.size[axis] as i64 {
+                return None;
+            }
+            t_max[axis] += t_delta[axis];
+        }
+    }
+}
+
+impl GameObject for VoxelGrid {
+    fn intersection_distance(&self, transform: &Transform, ray: &Ray) -> Option<f32> {
+        Some(self.raycast(transform, ray)?.distance)
+    }
+
+    /// Finds the face of a non-empty voxel which contains `point` and looks to an empty voxel.
+    fn normal(&self, transform: &Transform, point: &Vector<f32>) -> Option<Vector<f32>> {
+        let point = transform.to_local_point(point)?;
+        if point.dim() != 3 {
+            return None;
+        }
+        let cell = [point[0].floor() as i64, point[1].floor() as i64, point[2].floor() as i64];
+        for axis in 0..3 {
+            let border = point[axis].round();
+            if (point[axis] - border).abs() > 1e-3 {
+                continue;
+            }
+            let mut below = cell;
+            below[axis] = border as i64 - 1;
+            let mut above = cell;
+            above[axis] = border as i64;
+            let mut normal = Vector::zero3();
+            match (self.is_solid(below), self.is_solid(above)) {
+                (true, false) => normal[axis] = 1.0,
+                (false, true) => normal[axis] = -1.0,
+                _ => continue,
+            }
+            return Some(normal);
+        }
+        None
+    }
+
+    fn bounding_box(&self, transform: &Transform) -> Option<Aabb> {
+        let size = self.size.map(|x| x as f32);
+        let local_box = Aabb::new(Vector::zero3(), Vector::from_xyz(size[0], size[1], size[2]))?;
+        transform.to_world_box(&local_box)
+    }
+}
+
+
+#[cfg(test)]
+mod voxel_grid_tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn ray(point: [f32; 3], direction: [f32; 3]) -> Ray {
+        Ray::new(Vector::new(point.to_vec()), Vector::new(direction.to_vec()))
+    }
+
+    #[test]
+    fn set_and_get() {
+        let mut grid = VoxelGrid::new(2, 3, 4);
+        assert_eq!(grid.get_voxel(1, 2, 3), Some(EMPTY_VOXEL));
+        grid.set_voxel(1, 2, 3, 7).unwrap();
+        assert_eq!(grid.get_voxel(1, 2, 3), Some(7));
+        assert_eq!(grid.get_voxel(2, 0, 0), None);
+        assert!(grid.set_voxel(0, 3, 0, 1).is_err());
+    }
+
+    #[test]
+    fn zero_size() {
+        let grid = VoxelGrid::new(0, 4, 4);
+        // Ray grazes the flat bounding box: it enters and exits at the same parameter.
+        assert_eq!(grid.raycast(&Transform::default(), &ray([-1.0, 1.0, 1.0], [1.0, 0.0, 0.0])), None);
+        assert_eq!(grid.intersection_distance(&Transform::default(), &ray([-1.0, 1.0, 1.0], [1.0, 0.0, 0.0])), None);
+    }
+
+    #[test]
+    fn hit_from_outside() {
+        let mut grid = VoxelGrid::new(4, 4, 4);
+        grid.set_voxel(2, 1, 1, 5).unwrap();
+        let hit = grid.raycast(&Transform::default(), &ray([-3.0, 1.5, 1.5], [1.0, 0.0, 0.0])).unwrap();
+        assert_eq!(hit, VoxelHit { distance: 5.0, voxel: [2, 1, 1], normal: [-1, 0, 0], material: 5 });
+    }
+
+    #[test]
+    fn hit_from_inside() {
+        let mut grid = VoxelGrid::new(4, 4, 4);
+        grid.set_voxel(1, 1, 0, 1).unwrap();
+        let hit = grid.raycast(&Transform::default(), &ray([1.5, 1.5, 3.5], [0.0, 0.0, -1.0])).unwrap();
+        assert_eq!(hit.voxel, [1, 1, 0]);
+        assert_eq!(hit.normal, [0, 0, 1]);
+        assert_eq!(hit.distance, 2.5);
+    }
+
+    #[test]
+    fn diagonal_traversal() {
+        let mut grid = VoxelGrid::new(8, 8, 8);
+        grid.set_voxel(5, 4, 0, 1).unwrap();
+        grid.set_voxel(3, 3, 0, 1).unwrap();
+        let hit = grid.raycast(&Transform::default(), &ray([0.5, 0.1, 0.5], [1.0, 1.0, 0.0])).unwrap();
+        assert_eq!(hit.voxel, [3, 3, 0]);
+        assert_eq!(hit.normal, [0, -1, 0]);
+        assert!((hit.distance - 2.9).abs() < 1e-5);
+    }
+
+    #[test]
+    fn miss() {
+        let mut grid = VoxelGrid::new(4, 4, 4);
+        grid.set_voxel(0, 0, 0, 1).unwrap();
+        assert!(grid.raycast(&Transform::default(), &ray([-3.0, 1.5, 1.5], [1.0, 0.0, 0.0])).is_none());
+        assert!(grid.raycast(&Transform::default(), &ray([-3.0, 0.5, 0.5], [-1.0, 0.0, 0.0])).is_none());
+        assert!(grid.raycast(&Transform::default(), &ray([-3.0, 10.0, 0.5], [1.0, 0.0, 0.0])).is_none());
+    }
+
+    #[test]
+    fn removed_voxel() {
+        let mut grid = VoxelGrid::new(4, 1, 1);
+        grid.fill([0, 0, 0], [3, 0, 0], 1);
+        grid.set_voxel(0, 0, 0, EMPTY_VOXEL).unwrap();
+        let hit = grid.raycast(&Transform::default(), &ray([-1.0, 0.5, 0.5], [1.0, 0.0, 0.0])).unwrap();
+        assert_eq!(hit.voxel, [1, 0, 0]);
+    }
+
+    #[test]
+    fn transformed() {
+        let mut grid = VoxelGrid::new(2, 2, 2);
+        grid.set_voxel(1, 1, 1, 1).unwrap();
+        let mut transform = Transform::new_from_coords(10.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+        transform.scale = 2.0;
+        // Voxel (1, 1, 1) occupies from (12, 2, 2) to (14, 4, 4) in the world.
+        let r = ray([13.0, 3.0, 10.0], [0.0, 0.0, -1.0]);
+        assert_eq!(grid.intersection_distance(&transform, &r), Some(6.0));
+        let normal = grid.normal(&transform, &Vector::from_xyz(13.0, 3.0, 4.0)).unwrap();
+        assert_eq!(normal, Vector::from_xyz(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn edit_between_frames() {
+        let grid = Rc::new(RefCell::new(VoxelGrid::new(4, 1, 1)));
+        let object = SceneObject::new(Transform::default(), Box::new(grid.clone()));
+        let r = ray([-1.0, 0.5, 0.5], [1.0, 0.0, 0.0]);
+        assert_eq!(object.game_object.intersection_distance(&object.transform, &r), None);
+        grid.borrow_mut().set_voxel(2, 0, 0, 1).unwrap();
+        assert_eq!(object.game_object.intersection_distance(&object.transform, &r), Some(3.0));
+    }
+}
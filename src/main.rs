@@ -38,13 +38,36 @@ fn main() {
         screen_height: 20,
         target_fps: 30,
         camera_fov: PI / 3.0,
-        camera_draw_distance: 0.0,
+        camera_draw_distance: 50.0,
+        camera_projection: Projection::Perspective,
+        fog_mode: FogMode::Exponential,
+        fog_color: Rgb::BLACK,
+        fog_density: 0.05,
+        shadow_samples: 4,
+        shadow_bias: 1e-3,
+        max_bounces: 3,
+        supersampling: 3,
+        jittered_sampling: false,
+        adaptive_supersampling: true,
+        ascii_ramp: String::from(DEFAULT_ASCII_RAMP),
+        edge_glyphs: true,
+        render_mode: RenderMode::HalfBlock,
+        color_mode: ColorMode::detect(),
+        dithering: Dithering::Ordered,
+        post_processes: vec![PostProcess::AmbientOcclusion { strength: 0.5 }, PostProcess::Vignette { strength: 0.3 }],
+        cell_aspect_ratio: utils::terminal_cell_aspect_ratio().unwrap_or(DEFAULT_CELL_ASPECT_RATIO),
     };
     let mut game = Game::<Event, EventSystem>::new(
         CoordinateSystem::default3(),
         config
     );    
 
+    // Optional ASCII map file switches game to 2.5d tile map mode.
+    if let Some(filepath) = std::env::args().nth(1) {
+        let tile_map = TileMap::load_from_file(&filepath).expect("Could not load tile map.");
+        game.set_tile_map(tile_map);
+    }
+
     game.init();
     game.start_loop();
 }
\ No newline at end of file
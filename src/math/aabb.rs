@@ -0,0 +1,176 @@
+use super::*;
+
+
+/// Axis aligned bounding box in N-dimensional space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Vector<f32>,
+    pub max: Vector<f32>,
+}
+
+impl Aabb {
+    /// Creates box with corners `min` and `max`.
+    /// If dimensions differ or `min` is greater than `max` in some coordinate returns `None`.
+    pub fn new(min: Vector<f32>, max: Vector<f32>) -> Option<Aabb> {
+        if min.dim() != max.dim() || (0..min.dim()).any(|i| min[i] > max[i]) {
+            return None;
+        }
+        Some(Aabb { min, max })
+    }
+
+    /// Creates box with `center` and half extents `radius` in every coordinate.
+    pub fn around(center: &Vector<f32>, radius: f32) -> Aabb {
+        let radius = radius.abs();
+        Aabb {
+            min: Vector::new((0..center.dim()).map(|i| center[i] - radius).collect()),
+            max: Vector::new((0..center.dim()).map(|i| center[i] + radius).collect()),
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.min.dim()
+    }
+
+    pub fn center(&self) -> Vector<f32> {
+        Vector::new((0..self.dim()).map(|i| 0.5 * (self.min[i] + self.max[i])).collect())
+    }
+
+    /// Returns index of coordinate in which box is the longest.
+    pub fn longest_axis(&self) -> usize {
+        (0..self.dim())
+            .max_by(|a, b| (self.max[*a] - self.min[*a]).total_cmp(&(self.max[*b] - self.min[*b])))
+            .unwrap_or(0)
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vector::new((0..self.dim()).map(|i| f32::min(self.min[i], other.min[i])).collect()),
+            max: Vector::new((0..self.dim()).map(|i| f32::max(self.max[i], other.max[i])).collect()),
+        }
+    }
+
+    /// Returns common part of boxes or `None` if they do not overlap.
+    pub fn intersection(&self, other: &Aabb) -> Option<Aabb> {
+        Aabb::new(
+            Vector::new((0..self.dim()).map(|i| f32::max(self.min[i], other.min[i])).collect()),
+            Vector::new((0..self.dim()).map(|i| f32::min(self.max[i], other.max[i])).collect()),
+        )
+    }
+
+    /// Returns true if boxes have at least one common point.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.dim() == other.dim() && (0..self.dim()).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    /// Returns squared distance from `point` to the nearest point of box, zero if point is inside.
+    pub fn square_distance(&self, point: &Vector<f32>) -> f32 {
+        (0..self.dim())
+            .map(|i| {
+                let nearest = point[i].clamp(self.min[i], self.max[i]);
+                (point[i] - nearest) * (point[i] - nearest)
+            })
+            .sum()
+    }
+
+    /// Returns sum of areas of 2d faces of box: half of surface area in 3d.
+    /// Used as a cost of box in surface area heuristic.
+    pub fn surface_area(&self) -> f32 {
+        let extents: Vec<f32> = (0..self.dim()).map(|i| self.max[i] - self.min[i]).collect();
+        let mut area = 0.0;
+        for i in 0..extents.len() {
+            for j in i + 1..extents.len() {
+                area += extents[i] * extents[j];
+            }
+        }
+        area
+    }
+
+    /// Returns interval of ray parameter where ray is inside of box clipped by `[t_min, t_max]`,
+    /// or `None` if ray misses box in this interval.
+    pub fn ray_intersection(&self, ray: &Ray, t_min: f32, t_max: f32) -> Option<(f32, f32)> {
+        if ray.point.dim() != self.dim() || ray.direction.dim() != self.dim() {
+            return None;
+        }
+        let (mut enter, mut exit) = (t_min, t_max);
+        for i in 0..self.dim() {
+            let inverse = 1.0 / ray.direction[i];
+            let mut t1 = (self.min[i] - ray.point[i]) * inverse;
+            let mut t2 = (self.max[i] - ray.point[i]) * inverse;
+            if t1 > t2 {
+                std::mem::swap(&mut t1, &mut t2);
+            }
+            // NaN appears for parallel ray on the box border, it does not clip the interval.
+            if t1 > enter {
+                enter = t1;
+            }
+            if t2 < exit {
+                exit = t2;
+            }
+            if enter > exit {
+                return None;
+            }
+        }
+        Some((enter, exit))
+    }
+}
+
+
+#[cfg(test)]
+mod aabb_tests {
+    use super::*;
+
+    fn unit_cube() -> Aabb {
+        Aabb::new(Vector::zero3(), Vector::one3()).unwrap()
+    }
+
+    #[test]
+    fn new_invalid() {
+        assert!(Aabb::new(Vector::one3(), Vector::zero3()).is_none());
+        assert!(Aabb::new(Vector::zero3(), Vector::from_xy(1.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let a = unit_cube();
+        let b = Aabb::around(&Vector::one3(), 0.5);
+        assert_eq!(a.union(&b), Aabb::new(Vector::zero3(), Vector::from_xyz(1.5, 1.5, 1.5)).unwrap());
+        assert_eq!(a.intersection(&b), Aabb::new(Vector::from_xyz(0.5, 0.5, 0.5), Vector::one3()));
+        assert_eq!(a.intersection(&Aabb::around(&Vector::from_xyz(3.0, 0.0, 0.0), 1.0)), None);
+    }
+
+    #[test]
+    fn intersects() {
+        assert!(unit_cube().intersects(&Aabb::around(&Vector::from_xyz(1.5, 0.5, 0.5), 0.5)));
+        assert!(!unit_cube().intersects(&Aabb::around(&Vector::from_xyz(1.6, 0.5, 0.5), 0.5)));
+    }
+
+    #[test]
+    fn square_distance() {
+        assert_eq!(unit_cube().square_distance(&Vector::from_xyz(0.5, 0.5, 0.5)), 0.0);
+        assert_eq!(unit_cube().square_distance(&Vector::from_xyz(3.0, 0.5, -1.0)), 5.0);
+    }
+
+    #[test]
+    fn surface_area() {
+        let a = Aabb::new(Vector::zero3(), Vector::from_xyz(1.0, 2.0, 3.0)).unwrap();
+        assert_eq!(a.surface_area(), 11.0);
+        assert_eq!(a.longest_axis(), 2);
+    }
+
+    #[test]
+    fn ray_intersection() {
+        let ray = Ray::new(Vector::from_xyz(-1.0, 0.5, 0.5), Vector::from_xyz(2.0, 0.0, 0.0));
+        assert_eq!(unit_cube().ray_intersection(&ray, 0.0, f32::INFINITY), Some((0.5, 1.0)));
+        assert_eq!(unit_cube().ray_intersection(&ray, 0.0, 0.4), None);
+        let ray = Ray::new(Vector::from_xyz(-1.0, 1.5, 0.5), Vector::from_xyz(1.0, 0.0, 0.0));
+        assert_eq!(unit_cube().ray_intersection(&ray, 0.0, f32::INFINITY), None);
+    }
+
+    #[test]
+    fn ray_intersection4d() {
+        let aabb = Aabb::around(&Vector::new(vec![0.0, 0.0, 0.0, 5.0]), 1.0);
+        let ray = Ray::new(Vector::new(vec![-3.0, 0.0, 0.0, 5.5]), Vector::new(vec![1.0, 0.0, 0.0, 0.0]));
+        assert_eq!(aabb.ray_intersection(&ray, 0.0, f32::INFINITY), Some((2.0, 4.0)));
+    }
+}
@@ -1,6 +1,6 @@
 use super::*;
 
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct CoordinateSystem {
     pub initial_point: Vector<f32>,
     pub base: Vec<Vector<f32>>
@@ -8,11 +8,137 @@ pub struct CoordinateSystem {
 
 impl CoordinateSystem {
     pub fn default3() -> CoordinateSystem {
-        CoordinateSystem { 
+        CoordinateSystem {
             initial_point: Vector::zero3(),
             base: vec![Vector::from_xyz(1.0, 0.0, 0.0),
                        Vector::from_xyz(0.0, 1.0, 0.0),
                        Vector::from_xyz(0.0, 0.0, 1.0)]
         }
     }
-}
\ No newline at end of file
+
+    /// Returns coordinate system of dimension `dim` with initial point in the origin
+    /// and the standard orthonormal base.
+    pub fn identity(dim: usize) -> CoordinateSystem {
+        CoordinateSystem {
+            initial_point: Vector::zero(dim),
+            base: (0..dim).map(|i| Vector::unit(dim, i)).collect(),
+        }
+    }
+
+    /// Returns orthonormal coordinate system where the first base vector is `direction`.
+    /// Other base vectors are made from the standard base with Gram-Schmidt process.
+    /// If `direction` can not be normalized or dimensions differ returns `None`.
+    pub fn from_direction(initial_point: Vector<f32>, direction: &Vector<f32>) -> Option<CoordinateSystem> {
+        let dim = initial_point.dim();
+        if direction.dim() != dim || direction.magnitude() == 0.0 {
+            return None;
+        }
+        let mut base = vec![direction.clone()];
+        base.extend((0..dim).map(|i| Vector::unit(dim, i)));
+        let mut coordinate_system = CoordinateSystem { initial_point, base };
+        coordinate_system.orthonormalize();
+        Some(coordinate_system)
+    }
+
+    /// Returns dimension of the space.
+    pub fn dim(&self) -> usize {
+        self.initial_point.dim()
+    }
+
+    /// Makes base orthonormal with Gram-Schmidt process keeping the order of vectors.
+    /// Vectors that are linearly dependent on previous ones are removed.
+    /// Use it to get rid of floating point errors after many rotations.
+    pub fn orthonormalize(&mut self) {
+        let mut orthonormal: Vec<Vector<f32>> = vec![];
+        for v in self.base.iter() {
+            let mut u = v.clone();
+            for e in orthonormal.iter() {
+                u = (u.clone() - e * u.dot_product(e)).unwrap();
+            }
+            if u.magnitude() > 1e-5 {
+                u.normalize();
+                orthonormal.push(u);
+            }
+        }
+        self.base = orthonormal;
+    }
+
+    /// Returns true if base vectors are unit and pairwise orthogonal up to `epsilon`.
+    pub fn is_orthonormal(&self, epsilon: f32) -> bool {
+        Matrix::gram_matrix(self.base.clone())
+            .approximately_equal(&Matrix::identity(self.base.len()), epsilon)
+    }
+
+    /// Rotates base vectors with indices `a` and `b` in the plane spanned by them.
+    /// Positive `radians` rotate `base[a]` towards `base[b]`.
+    /// Other base vectors stay the same.
+    pub fn rotate(&mut self, a: usize, b: usize, radians: f32) -> Result<(), ()> {
+        if a == b || a >= self.base.len() || b >= self.base.len() {
+            return Err(());
+        }
+        let (cos, sin) = (radians.cos(), radians.sin());
+        let base_a = (&self.base[a] * cos + &self.base[b] * sin).ok_or(())?;
+        let base_b = (&self.base[b] * cos - &self.base[a] * sin).ok_or(())?;
+        self.base[a] = base_a;
+        self.base[b] = base_b;
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod coordinate_system_tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn identity4() {
+        let cs = CoordinateSystem::identity(4);
+        assert_eq!(cs.dim(), 4);
+        assert!(cs.is_orthonormal(1e-6));
+        assert_eq!(cs.base[3], Vector::new(vec![0.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn from_direction() {
+        let cs = CoordinateSystem::from_direction(
+            Vector::zero(4),
+            &Vector::new(vec![0.0, 2.0, 0.0, 0.0])
+        ).unwrap();
+        assert_eq!(cs.base.len(), 4);
+        assert!(cs.is_orthonormal(1e-6));
+        assert_eq!(cs.base[0], Vector::new(vec![0.0, 1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn from_zero_direction() {
+        assert!(CoordinateSystem::from_direction(Vector::zero3(), &Vector::zero3()).is_none());
+    }
+
+    #[test]
+    fn rotate_xw() {
+        let mut cs = CoordinateSystem::identity(4);
+        cs.rotate(0, 3, PI / 2.0).unwrap();
+        assert!(cs.base[0].approximately_equal(&Vector::new(vec![0.0, 0.0, 0.0, 1.0]), 1e-6));
+        assert!(cs.base[3].approximately_equal(&Vector::new(vec![-1.0, 0.0, 0.0, 0.0]), 1e-6));
+        assert!(cs.is_orthonormal(1e-6));
+    }
+
+    #[test]
+    fn rotate_same_axis() {
+        let mut cs = CoordinateSystem::identity(4);
+        assert!(cs.rotate(2, 2, 1.0).is_err());
+        assert!(cs.rotate(0, 4, 1.0).is_err());
+    }
+
+    #[test]
+    fn orthonormalize_after_rotations() {
+        let mut cs = CoordinateSystem::identity(4);
+        for i in 0..1000 {
+            cs.rotate(i % 4, (i + 1) % 4, 0.1).unwrap();
+        }
+        cs.orthonormalize();
+        assert!(cs.is_orthonormal(1e-5));
+    }
+}
@@ -0,0 +1,142 @@
+/// Closed interval `[enter, exit]` of ray parameter where ray is inside of an object.
+/// Bounds may be infinite.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub enter: f32,
+    pub exit: f32,
+}
+
+impl Interval {
+    pub fn new(enter: f32, exit: f32) -> Interval {
+        Interval { enter, exit }
+    }
+
+    /// Interval which contains all real numbers.
+    pub fn everything() -> Interval {
+        Interval::new(f32::NEG_INFINITY, f32::INFINITY)
+    }
+
+    pub fn contains(&self, t: f32) -> bool {
+        self.enter <= t && t <= self.exit
+    }
+
+    /// Returns union of two lists of sorted disjoint intervals.
+    /// Result is sorted and disjoint too.
+    pub fn union(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+        let mut all: Vec<Interval> = a.iter().chain(b.iter()).copied().collect();
+        all.sort_by(|x, y| x.enter.total_cmp(&y.enter));
+
+        let mut result: Vec<Interval> = vec![];
+        for interval in all {
+            match result.last_mut() {
+                Some(last) if interval.enter <= last.exit => {
+                    last.exit = f32::max(last.exit, interval.exit);
+                }
+                _ => result.push(interval),
+            }
+        }
+        result
+    }
+
+    /// Returns intersection of two lists of sorted disjoint intervals.
+    /// Result is sorted and disjoint too.
+    pub fn intersection(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+        let mut result = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            let enter = f32::max(a[i].enter, b[j].enter);
+            let exit = f32::min(a[i].exit, b[j].exit);
+            if enter <= exit {
+                result.push(Interval::new(enter, exit));
+            }
+            if a[i].exit < b[j].exit {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        result
+    }
+
+    /// Returns intervals of `a` without intervals of `b`.
+    /// Both lists must be sorted and disjoint. Result is sorted and disjoint too.
+    pub fn difference(a: &[Interval], b: &[Interval]) -> Vec<Interval> {
+        let mut result = vec![];
+        for interval in a {
+            let mut enter = interval.enter;
+            let mut covered = false;
+            for subtrahend in b {
+                if subtrahend.exit < enter || subtrahend.enter > interval.exit {
+                    continue;
+                }
+                if subtrahend.enter > enter {
+                    result.push(Interval::new(enter, subtrahend.enter));
+                }
+                if subtrahend.exit >= interval.exit {
+                    covered = true;
+                    break;
+                }
+                enter = subtrahend.exit;
+            }
+            if !covered {
+                result.push(Interval::new(enter, interval.exit));
+            }
+        }
+        result
+    }
+}
+
+
+#[cfg(test)]
+mod interval_tests {
+    use super::*;
+
+    fn intervals(bounds: &[(f32, f32)]) -> Vec<Interval> {
+        bounds.iter().map(|(enter, exit)| Interval::new(*enter, *exit)).collect()
+    }
+
+    #[test]
+    fn union() {
+        let a = intervals(&[(0.0, 2.0), (5.0, 6.0)]);
+        let b = intervals(&[(1.0, 3.0), (7.0, 8.0)]);
+        assert_eq!(Interval::union(&a, &b), intervals(&[(0.0, 3.0), (5.0, 6.0), (7.0, 8.0)]));
+    }
+
+    #[test]
+    fn intersection() {
+        let a = intervals(&[(0.0, 2.0), (5.0, 6.0)]);
+        let b = intervals(&[(1.0, 5.5)]);
+        assert_eq!(Interval::intersection(&a, &b), intervals(&[(1.0, 2.0), (5.0, 5.5)]));
+    }
+
+    #[test]
+    fn intersection_disjoint() {
+        let a = intervals(&[(0.0, 1.0)]);
+        let b = intervals(&[(2.0, 3.0)]);
+        assert_eq!(Interval::intersection(&a, &b), vec![]);
+    }
+
+    #[test]
+    fn difference_hole() {
+        let a = intervals(&[(0.0, 10.0)]);
+        let b = intervals(&[(2.0, 3.0), (5.0, 6.0)]);
+        assert_eq!(Interval::difference(&a, &b), intervals(&[(0.0, 2.0), (3.0, 5.0), (6.0, 10.0)]));
+    }
+
+    #[test]
+    fn difference_covered() {
+        let a = intervals(&[(0.0, 1.0), (4.0, 5.0)]);
+        let b = intervals(&[(-1.0, 2.0), (4.5, f32::INFINITY)]);
+        assert_eq!(Interval::difference(&a, &b), intervals(&[(4.0, 4.5)]));
+    }
+
+    #[test]
+    fn difference_infinite() {
+        let a = vec![Interval::everything()];
+        let b = intervals(&[(1.0, 2.0)]);
+        assert_eq!(
+            Interval::difference(&a, &b),
+            intervals(&[(f32::NEG_INFINITY, 1.0), (2.0, f32::INFINITY)])
+        );
+    }
+}
@@ -338,6 +338,41 @@ impl Matrix<f32> {
         Matrix { rows_count, cols_count, rows }
     }
 
+    /// Returns a square matrix with `diagonal` elements on the main diagonal and zeroes elsewhere.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rusty_gaym_engine::math::*;
+    /// let m = Matrix::from_diagonal(&Vector::from_xy(2.0, 3.0));
+    /// assert_eq!(m, Matrix::new(2, 2, vec![2.0, 0.0,
+    ///                                      0.0, 3.0]).unwrap());
+    /// ```
+    pub fn from_diagonal(diagonal: &Vector<f32>) -> Matrix<f32> {
+        Matrix::from_rule(diagonal.dim(), diagonal.dim(), |i, j|
+        if i == j {
+            diagonal[i]
+        } else {
+            0.0
+        })
+    }
+
+    /// Returns true if matrix is square and equal to its transpose
+    /// up to `epsilon` for every element.
+    pub fn is_symmetric(&self, epsilon: f32) -> bool {
+        self.rows_count == self.cols_count &&
+            self.approximately_equal(&self.transposed(), epsilon)
+    }
+
+    /// Returns value of the bilinear form `x^T * self * y`.
+    /// If dimensions of `x` or `y` do not match the matrix returns `None`.
+    pub fn bilinear_form(&self, x: &Vector<f32>, y: &Vector<f32>) -> Option<f32> {
+        if x.dim() != self.rows_count || y.dim() != self.cols_count {
+            return None;
+        }
+        Some(x.dot_product(&self.multiply_by_vector(y)?))
+    }
+
     pub fn gram_matrix(bases: Vec<Vector<f32>>) -> Matrix<f32> {
         Matrix::from_rule(bases.len(), bases.len(), |i, j| {
             bases[i].dot_product(&bases[j])
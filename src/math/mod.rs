@@ -1,10 +1,14 @@
+pub mod aabb;
 pub mod coordinate_system;
+pub mod interval;
 pub mod matrix;
 pub mod ray;
 pub mod vector;
 pub mod vector_space;
 
+pub use self::aabb::*;
 pub use self::coordinate_system::*;
+pub use self::interval::*;
 pub use self::matrix::*;
 pub use self::ray::*;
 pub use self::vector::*;
@@ -21,4 +21,9 @@ impl Ray {
     pub fn normalize(&mut self) {
         self.direction.normalize();
     }
+
+    /// Returns point `point + t * direction`.
+    pub fn point_at(&self, t: f32) -> Vector<f32> {
+        (&self.point + &(&self.direction * t)).unwrap()
+    }
 }
\ No newline at end of file
@@ -55,6 +55,19 @@ where Copyable: Copy {
 }
 
 impl Vector<f32> {
+    /// Returns zero vector of dimension `dim`.
+    pub fn zero(dim: usize) -> Vector<f32> {
+        Vector::new(vec![0.0; dim])
+    }
+
+    /// Returns `index`-th vector of the standard basis of dimension `dim`.
+    /// If `index` is out of range returns zero vector.
+    pub fn unit(dim: usize, index: usize) -> Vector<f32> {
+        let mut v = Vector::zero(dim);
+        let _ = v.set(index, 1.0);
+        v
+    }
+
     pub fn zero3() -> Vector<f32> {
         Vector::from_xyz(0.0, 0.0, 0.0)
     }
@@ -71,6 +84,14 @@ impl Vector<f32> {
         Vector::new(vec![x, y])
     }
 
+    /// Returns a copy of the vector with `value` appended as the last component.
+    /// Useful to get homogeneous coordinates: `1.0` for points and `0.0` for directions.
+    pub fn extended(&self, value: f32) -> Vector<f32> {
+        let mut elements = self.elements.clone();
+        elements.push(value);
+        Vector::new(elements)
+    }
+
     /// Rotates 2d vector counterclockwise on angle expressed in radians.
     /// Returns rotated 2d vector.
     /// If provided vector is not 2d, returns `None`.
@@ -132,14 +153,14 @@ impl Vector<f32> {
 
     /// Returns the square of Euclidean magnitude of vector.
     pub fn square_magnitude(&self) -> f32 {
-        self.square_distance(&Vector::zero3())
+        self.square_distance(&Vector::zero(self.dim()))
     }
 
     /// Returns Euclidean magnitude of vector.
     /// This method first calculates the square magnitude, and then takes the square root of result.
     /// If you want to get square magnitude use `square_magnitude` method instead.
     pub fn magnitude(&self) -> f32 {
-        self.distance(&Vector::zero3())
+        self.distance(&Vector::zero(self.dim()))
     }
 
     /// Computes the dot product of this vector with another vector.
@@ -308,6 +329,15 @@ where T: Sub<Output = T> + Neg<Output = T> + Add<Output = T> {
     }
 }
 
+impl<T> Sub<&Vector<T>> for &Vector<T>
+where T: Sub<Output = T> + Neg<Output = T> + Add<Output = T> + Clone {
+    type Output = Option<Vector<T>>;
+
+    fn sub(self, rhs: &Vector<T>) -> Self::Output {
+        self.clone() - rhs.clone()
+    }
+}
+
 
 impl<T> Neg for Vector<T>
 where T: Neg<Output = T> {
@@ -465,6 +495,12 @@ mod tests {
         compare_vectors(&actual, &expected, true, None);
     }
     
+    #[test]
+    fn magnitude5d() {
+        let v = Vector::new(vec![1.0, 1.0, 1.0, 1.0, 2.0]);
+        assert_eq!(v.square_magnitude(), 8.0);
+    }
+
     #[test]
     fn vector_equal1() {
         let a = Vector::from_xyz(1.0, 2.0, 3.0);
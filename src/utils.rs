@@ -1,7 +1,106 @@
 /// Calculates vertical fov if horizontal fov is known.
-/// Aspect ratio is a ratio of the screen width to its height. 
+/// Aspect ratio is a ratio of the screen width to its height in physical units, not in cells.
 pub fn vertical_fov_from_horizontal(horizontal_fov: f32, aspect_ratio: f32) -> f32 {
-    (aspect_ratio * (0.5 * horizontal_fov).tan()).atan()
+    2.0 * ((0.5 * horizontal_fov).tan() / aspect_ratio).atan()
+}
+
+/// Returns ratio of width of terminal cell to its height if terminal reports its size in pixels.
+/// Many terminals report zero pixel size, then `None` is returned.
+#[cfg(unix)]
+pub fn terminal_cell_aspect_ratio() -> Option<f32> {
+    let mut size = libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+    // SAFETY: `TIOCGWINSZ` only writes to `size` which lives until the end of the call.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if result != 0 || size.ws_row == 0 || size.ws_col == 0 || size.ws_xpixel == 0 || size.ws_ypixel == 0 {
+        return None;
+    }
+    let cell_width = size.ws_xpixel as f32 / size.ws_col as f32;
+    let cell_height = size.ws_ypixel as f32 / size.ws_row as f32;
+    Some(cell_width / cell_height)
+}
+
+#[cfg(not(unix))]
+pub fn terminal_cell_aspect_ratio() -> Option<f32> {
+    None
+}
+
+/// Solves quadratic equation `a * t^2 + b * t + c = 0`.
+/// Returns real roots in ascending order or `None` if there are no real roots.
+/// If `a` is zero the equation is solved as linear and both roots are the same.
+pub fn solve_quadratic(a: f32, b: f32, c: f32) -> Option<(f32, f32)> {
+    if a.abs() < f32::EPSILON {
+        if b.abs() < f32::EPSILON {
+            return None;
+        }
+        let t = -c / b;
+        return Some((t, t));
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
+    let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
+    Some((f32::min(t1, t2), f32::max(t1, t2)))
+}
+
+/// Returns next whitespace separated token of PGM or PPM header or plain raster, skipping comments.
+pub fn next_netpbm_token(bytes: &[u8], position: &mut usize) -> Option<String> {
+    loop {
+        match bytes.get(*position)? {
+            b'#' => while *bytes.get(*position)? != b'\n' {
+                *position += 1;
+            },
+            byte if byte.is_ascii_whitespace() => *position += 1,
+            _ => break,
+        }
+    }
+    let start = *position;
+    while bytes.get(*position).is_some_and(|byte| !byte.is_ascii_whitespace()) {
+        *position += 1;
+    }
+    Some(String::from_utf8_lossy(&bytes[start..*position]).into_owned())
+}
+
+/// Small xorshift pseudo-random generator for jittered sampling.
+/// The same seed always gives the same sequence, so rendering is reproducible.
+#[derive(Debug, Clone)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        // Seeds are scrambled by splitmix64, so close seeds give unrelated sequences.
+        let mut z = seed.wrapping_add(0x9E3779B97F4A7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^= z >> 31;
+        // Zero state would give zeros forever.
+        XorShift { state: if z == 0 { 1 } else { z } }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Returns number uniformly distributed in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns point uniformly distributed inside of unit ball of dimension `dim`.
+    pub fn in_unit_ball(&mut self, dim: usize) -> crate::math::Vector<f32> {
+        loop {
+            let point = crate::math::Vector::new((0..dim).map(|_| 2.0 * self.next_f32() - 1.0).collect());
+            if point.square_magnitude() <= 1.0 {
+                return point;
+            }
+        }
+    }
 }
 
 pub fn assert_eq_f32(lhs: f32, rhs: f32) {